@@ -1,7 +1,9 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_2024_rs::*;
 
+const DEBUG: bool = false;
+
 struct Manual {
     rules: Vec<(i32, i32)>,
     pages: Vec<Vec<i32>>,
@@ -62,7 +64,7 @@ fn parse_input(input: String) -> Manual {
 // probably then want to assert that the set of page entries is the set of rules elements
 // on initial parse above.
 
-fn is_correct(rules: &Vec<(i32, i32)>, page: &[i32]) -> bool {
+fn is_correct(rules: &[(i32, i32)], page: &[i32]) -> bool {
     let mut indices = HashMap::new();
     for (i, c) in page.iter().enumerate() {
         indices.insert(c, i);
@@ -78,36 +80,173 @@ fn is_correct(rules: &Vec<(i32, i32)>, page: &[i32]) -> bool {
     true
 }
 
-fn re_order(rules: &Vec<(i32, i32)>, page: &[i32]) -> Vec<i32> {
-    let mut ordered = page.to_owned();
-    while !is_correct(rules, &ordered) {
-        for (a, b) in rules {
-            // NOTE: It's faster to seek every time than to mutate the indices HashMap with
-            // indices.remove() and indices.insert() again.
-            if let (Some(ia), Some(ib)) = (
-                ordered.iter().position(|v| v == a),
-                ordered.iter().position(|v| v == b),
-            ) {
-                // NOTE: Forgot this if-condition and debugged for a few minutes because the
-                // loop went infinite.
-                if ia > ib {
-                    ordered.swap(ia, ib);
+// Page -> every page a rule says has to come after it.
+type RuleGraph = HashMap<i32, HashSet<i32>>;
+
+fn get_rule_graph(rules: &[(i32, i32)]) -> RuleGraph {
+    let mut graph: RuleGraph = HashMap::new();
+    for (a, b) in rules {
+        graph.entry(*a).or_default().insert(*b);
+    }
+    graph
+}
+
+fn has_rule(graph: &RuleGraph, a: i32, b: i32) -> bool {
+    graph.get(&a).is_some_and(|after| after.contains(&b))
+}
+
+#[derive(Debug, PartialEq)]
+enum OrderError {
+    // Pages (sorted) that appear in the update more than once.
+    Duplicate(Vec<i32>),
+    // A shortest loop of rules, starting from its smallest page: each page has to come before
+    // the next one, and the last before the first.
+    Cycle(Vec<i32>),
+    // After placing these pages, more than one page (sorted) could have gone next.
+    Ambiguous { placed: Vec<i32>, choices: Vec<i32> },
+}
+
+fn shortest_cycle(graph: &RuleGraph, pages: &[i32]) -> Option<Vec<i32>> {
+    // BFS out from each page over rules between these pages only, until it gets back round.
+    let mut pages = pages.to_vec();
+    pages.sort();
+    let mut best: Option<Vec<i32>> = None;
+    for start in &pages {
+        let mut parents: HashMap<i32, i32> = HashMap::new();
+        let mut queue = VecDeque::from([*start]);
+        while let Some(at) = queue.pop_front() {
+            if has_rule(graph, at, *start) {
+                let mut cycle = vec![at];
+                while let Some(prev) = parents.get(cycle.last().unwrap()) {
+                    cycle.push(*prev);
+                }
+                cycle.reverse();
+                if best.as_ref().is_none_or(|b| cycle.len() < b.len()) {
+                    best = Some(cycle);
+                }
+                break;
+            }
+            for next in &pages {
+                if next != start && !parents.contains_key(next) && has_rule(graph, at, *next) {
+                    parents.insert(*next, at);
+                    queue.push_back(*next);
+                }
+            }
+        }
+    }
+    best
+}
+
+fn topo_order(graph: &RuleGraph, page: &[i32]) -> Result<Vec<i32>, OrderError> {
+    // Kahn's algorithm over only the rules between pages in this update. The puzzle assumes
+    // there's exactly one correct order, which means there must only ever be one page ready to go
+    // next, so anything else is an error rather than picking one.
+    let mut seen = HashSet::new();
+    let mut duplicates: Vec<i32> = page.iter().filter(|p| !seen.insert(**p)).cloned().collect();
+    if !duplicates.is_empty() {
+        duplicates.sort();
+        duplicates.dedup();
+        return Err(OrderError::Duplicate(duplicates));
+    }
+
+    let mut incoming: HashMap<i32, usize> = page.iter().map(|p| (*p, 0)).collect();
+    for a in page {
+        for b in page {
+            if has_rule(graph, *a, *b) {
+                *incoming.get_mut(b).unwrap() += 1;
+            }
+        }
+    }
+
+    let mut ordered = Vec::new();
+    while ordered.len() < page.len() {
+        let mut ready: Vec<i32> = incoming
+            .iter()
+            .filter(|(_, count)| **count == 0)
+            .map(|(p, _)| *p)
+            .collect();
+        ready.sort();
+        match ready.len() {
+            0 => {
+                // Everything left is waiting on something else that's left, so there's a loop
+                // in there somewhere, but not every page left has to be part of it.
+                let stuck: Vec<i32> = incoming.keys().cloned().collect();
+                let cycle = shortest_cycle(graph, &stuck).unwrap();
+                return Err(OrderError::Cycle(cycle));
+            }
+            1 => {}
+            _ => {
+                return Err(OrderError::Ambiguous {
+                    placed: ordered,
+                    choices: ready,
+                })
+            }
+        }
+        let next = ready[0];
+        incoming.remove(&next);
+        for b in page {
+            if has_rule(graph, next, *b) {
+                if let Some(count) = incoming.get_mut(b) {
+                    *count -= 1;
                 }
             }
         }
+        ordered.push(next);
+    }
+
+    Ok(ordered)
+}
+
+/// Pairs of pages in the update that no rule orders either way.
+fn missing_rules(graph: &RuleGraph, page: &[i32]) -> Vec<(i32, i32)> {
+    let mut missing = Vec::new();
+    for (i, a) in page.iter().enumerate() {
+        for b in &page[i + 1..] {
+            if a != b && !has_rule(graph, *a, *b) && !has_rule(graph, *b, *a) {
+                missing.push((*a, *b));
+            }
+        }
     }
+    missing
+}
 
-    ordered
+fn get_problems(parsed: &Manual) -> Vec<String> {
+    let graph = get_rule_graph(&parsed.rules);
+    let mut problems = Vec::new();
+    for (i, page) in parsed.pages.iter().enumerate() {
+        for (a, b) in missing_rules(&graph, page) {
+            problems.push(format!("Update {}: no rule for {} and {}.", i, a, b));
+        }
+        match topo_order(&graph, page) {
+            Ok(_) => {}
+            Err(OrderError::Duplicate(pages)) => {
+                problems.push(format!("Update {}: {:?} listed more than once.", i, pages));
+            }
+            Err(OrderError::Cycle(cycle)) => {
+                problems.push(format!("Update {}: rules form a cycle {:?}.", i, cycle));
+            }
+            Err(OrderError::Ambiguous { placed, choices }) => problems.push(format!(
+                "Update {}: after {:?} any of {:?} could go next.",
+                i, placed, choices
+            )),
+        }
+    }
+    problems
 }
 
 fn solve(parsed: Manual) -> i32 {
+    let graph = get_rule_graph(&parsed.rules);
+
     let mut corrected_pages = Vec::new();
 
     for page in parsed.pages {
         if is_correct(&parsed.rules, &page) {
             continue;
         }
-        corrected_pages.push(re_order(&parsed.rules, &page));
+        corrected_pages.push(
+            topo_order(&graph, &page)
+                .unwrap_or_else(|e| panic!("Cannot re-order {:?}: {:?}", page, e)),
+        );
     }
 
     corrected_pages
@@ -119,6 +258,11 @@ fn solve(parsed: Manual) -> i32 {
 fn main() {
     let input = load_input(2024, 5);
     let parsed = parse_input(input);
+    if DEBUG {
+        for problem in get_problems(&parsed) {
+            println!("{}", problem);
+        }
+    }
     let answer = solve(parsed);
     println!("Answer: {:?}", answer);
 }
@@ -176,16 +320,102 @@ mod tests {
             assert_eq!(expect, is_correct(&parsed.rules, page));
         }
 
+        let graph = get_rule_graph(&parsed.rules);
         assert_eq!(
-            vec![97, 75, 47, 61, 53],
-            re_order(&parsed.rules, &parsed.pages[3])
+            Ok(vec![97, 75, 47, 61, 53]),
+            topo_order(&graph, &parsed.pages[3])
         );
-        assert_eq!(vec![61, 29, 13], re_order(&parsed.rules, &parsed.pages[4]));
+        assert_eq!(Ok(vec![61, 29, 13]), topo_order(&graph, &parsed.pages[4]));
         assert_eq!(
-            vec![97, 75, 47, 29, 13],
-            re_order(&parsed.rules, &parsed.pages[5])
+            Ok(vec![97, 75, 47, 29, 13]),
+            topo_order(&graph, &parsed.pages[5])
         );
 
         assert_eq!(123, solve(parsed));
     }
+
+    #[test]
+    fn day05b_problems() {
+        let input = "
+47|53
+97|13
+97|61
+97|47
+75|29
+61|13
+75|53
+29|13
+97|29
+53|29
+61|53
+97|53
+61|29
+47|13
+75|47
+97|75
+47|61
+75|61
+47|29
+75|13
+53|13
+13|97
+
+75,47,61,53,29
+97,61,53,29,13
+47,99,61
+        "
+        .to_string();
+        let parsed = parse_input(input);
+        let graph = get_rule_graph(&parsed.rules);
+
+        assert_eq!(
+            Ok(vec![75, 47, 61, 53, 29]),
+            topo_order(&graph, &[29, 61, 75, 53, 47])
+        );
+        assert_eq!(
+            Err(OrderError::Cycle(vec![13, 97])),
+            topo_order(&graph, &parsed.pages[1])
+        );
+        assert_eq!(
+            Err(OrderError::Ambiguous {
+                placed: vec![],
+                choices: vec![47, 99]
+            }),
+            topo_order(&graph, &parsed.pages[2])
+        );
+        assert_eq!(
+            vec![(47, 99), (99, 61)],
+            missing_rules(&graph, &parsed.pages[2])
+        );
+        assert!(missing_rules(&graph, &parsed.pages[0]).is_empty());
+
+        assert_eq!(
+            vec![
+                "Update 1: rules form a cycle [13, 97].",
+                "Update 2: no rule for 47 and 99.",
+                "Update 2: no rule for 99 and 61.",
+                "Update 2: after [] any of [47, 99] could go next.",
+            ],
+            get_problems(&parsed)
+        );
+
+        // 3 is stuck behind the loop between 1 and 2, but isn't part of it.
+        let graph = get_rule_graph(&[(1, 2), (2, 1), (2, 3)]);
+        assert_eq!(
+            Err(OrderError::Cycle(vec![1, 2])),
+            topo_order(&graph, &[3, 2, 1])
+        );
+        // Two loops the same length, the one through the smallest page wins.
+        let graph = get_rule_graph(&[(1, 2), (2, 3), (3, 1), (3, 4), (4, 2)]);
+        assert_eq!(
+            Err(OrderError::Cycle(vec![1, 2, 3])),
+            topo_order(&graph, &[1, 2, 3, 4])
+        );
+
+        assert_eq!(
+            Err(OrderError::Duplicate(vec![1])),
+            topo_order(&graph, &[1, 2, 1, 1])
+        );
+        assert!(missing_rules(&graph, &[1, 2, 1]).is_empty());
+    }
 }
@@ -1,38 +1,196 @@
 use aoc_2024_rs::*;
 
+const DEBUG: bool = false;
+
 #[derive(Debug, PartialEq)]
 struct Equation {
-    value: u64,
-    parts: Vec<u64>,
+    value: u128,
+    parts: Vec<u128>,
 }
 
-#[derive(Debug, Clone, Copy)]
-enum Operator {
-    Add,
-    Mul,
-    Cat,
+// What the left hand side must have been for `lhs op rhs == result`.
+enum Inverse {
+    Impossible,
+    Exactly(u128),
+    // Could be any of many values (like for %), so it has to be found going forwards instead.
+    Unknown,
 }
 
-impl Operator {
-    fn apply(&self, lhs: u64, rhs: u64) -> u64 {
-        match self {
-            Operator::Add => lhs + rhs,
-            Operator::Mul => lhs * rhs,
-            Operator::Cat => {
-                // Adding these simpler preconditions saved a little bit of time.
-                // Another tip I saw on the subreddit.
-                if rhs < 10 {
-                    lhs * 10 + rhs
-                } else if rhs < 100 {
-                    lhs * 100 + rhs
-                } else if rhs < 1_000 {
-                    lhs * 1_000 + rhs
-                } else {
-                    // I did look up this formula but was aware of it.
-                    lhs * (10u64.pow(rhs.ilog10() + 1)) + rhs
-                }
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Mode {
+    // What the puzzle uses, operators are applied as they come.
+    LeftToRight,
+    // Normal precedence, see Operator::precedence. There's nothing to prune on here, so every
+    // one of operators^(parts - 1) sequences gets evaluated: 3 operators over 12 parts is already
+    // ~177k sequences per equation, so it's only really usable on short equations.
+    #[allow(dead_code)]
+    Precedence,
+}
+
+/// An operator is just a table entry, so new ones can be plugged into the solver by defining
+/// another const like the ones below. Anything that would overflow, go negative or divide by zero
+/// returns None and just doesn't count as a solution.
+#[derive(Clone, Copy)]
+struct Operator {
+    symbol: &'static str,
+    // Higher binds tighter, only used by Mode::Precedence.
+    precedence: u8,
+    right_associative: bool,
+    forward: fn(u128, u128) -> Option<u128>,
+    // Given result and rhs, see Inverse.
+    inverse: fn(u128, u128) -> Inverse,
+}
+
+impl std::fmt::Debug for Operator {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{}", self.symbol)
+    }
+}
+
+impl PartialEq for Operator {
+    fn eq(&self, other: &Self) -> bool {
+        self.symbol == other.symbol
+    }
+}
+
+fn exactly(lhs: Option<u128>) -> Inverse {
+    match lhs {
+        Some(lhs) => Inverse::Exactly(lhs),
+        None => Inverse::Impossible,
+    }
+}
+
+const ADD: Operator = Operator {
+    symbol: "+",
+    precedence: 1,
+    right_associative: false,
+    forward: |lhs, rhs| lhs.checked_add(rhs),
+    inverse: |result, rhs| exactly(result.checked_sub(rhs)),
+};
+
+const MUL: Operator = Operator {
+    symbol: "*",
+    precedence: 2,
+    right_associative: false,
+    forward: |lhs, rhs| lhs.checked_mul(rhs),
+    inverse: |result, rhs| {
+        if rhs == 0 {
+            if result == 0 {
+                Inverse::Unknown
+            } else {
+                Inverse::Impossible
+            }
+        } else if result.is_multiple_of(rhs) {
+            Inverse::Exactly(result / rhs)
+        } else {
+            Inverse::Impossible
+        }
+    },
+};
+
+// Concatenation glues numbers together, so it binds tighter than arithmetic.
+const CAT: Operator = Operator {
+    symbol: "||",
+    precedence: 3,
+    right_associative: false,
+    forward: |lhs, rhs| {
+        // Adding these simpler preconditions saved a little bit of time.
+        // Another tip I saw on the subreddit.
+        if rhs < 10 {
+            lhs.checked_mul(10)?.checked_add(rhs)
+        } else if rhs < 100 {
+            lhs.checked_mul(100)?.checked_add(rhs)
+        } else if rhs < 1_000 {
+            lhs.checked_mul(1_000)?.checked_add(rhs)
+        } else {
+            // I did look up this formula but was aware of it.
+            lhs.checked_mul(10u128.checked_pow(rhs.ilog10() + 1)?)?
+                .checked_add(rhs)
+        }
+    },
+    inverse: |result, rhs| {
+        // The result has to end with the digits of rhs, then strip them off. If there's no
+        // room for another digit in front of rhs, going forwards couldn't have made it either.
+        let Some(shift) = 10u128.checked_pow(if rhs == 0 { 1 } else { rhs.ilog10() + 1 }) else {
+            return Inverse::Impossible;
+        };
+        match result.checked_sub(rhs) {
+            Some(rest) if rest % shift == 0 => Inverse::Exactly(rest / shift),
+            _ => Inverse::Impossible,
+        }
+    },
+};
+
+// Not in the puzzle, but can be plugged into the solver.
+#[allow(dead_code)]
+const SUB: Operator = Operator {
+    symbol: "-",
+    precedence: 1,
+    right_associative: false,
+    forward: |lhs, rhs| lhs.checked_sub(rhs),
+    inverse: |result, rhs| exactly(result.checked_add(rhs)),
+};
+
+#[allow(dead_code)]
+const REM: Operator = Operator {
+    symbol: "%",
+    precedence: 2,
+    right_associative: false,
+    forward: |lhs, rhs| lhs.checked_rem(rhs),
+    inverse: |_, _| Inverse::Unknown,
+};
+
+#[allow(dead_code)]
+const POW: Operator = Operator {
+    symbol: "^",
+    precedence: 4,
+    right_associative: true,
+    forward: |lhs, rhs| lhs.checked_pow(u32::try_from(rhs).ok()?),
+    inverse: |result, rhs| match rhs {
+        // Anything to the 0 is 1.
+        0 if result == 1 => Inverse::Unknown,
+        0 => Inverse::Impossible,
+        _ => {
+            // Powers are increasing, so there's at most one whole number root.
+            let exponent = u32::try_from(rhs).unwrap_or(u32::MAX);
+            let root = integer_root(result, exponent);
+            if root.checked_pow(exponent) == Some(result) {
+                Inverse::Exactly(root)
+            } else {
+                Inverse::Impossible
             }
         }
+    },
+};
+
+/// The largest r with r^n <= value (n > 0).
+fn integer_root(value: u128, n: u32) -> u128 {
+    if n == 1 {
+        return value;
+    }
+    let (mut lo, mut hi) = (0, value.min(1 << (128 / n)) + 1);
+    while hi - lo > 1 {
+        let mid = lo + (hi - lo) / 2;
+        match mid.checked_pow(n) {
+            Some(p) if p <= value => lo = mid,
+            _ => hi = mid,
+        }
+    }
+    lo
+}
+
+impl Operator {
+    #[allow(dead_code)]
+    fn apply(&self, lhs: u128, rhs: u128) -> u128 {
+        self.checked_apply(lhs, rhs).unwrap()
+    }
+
+    fn checked_apply(&self, lhs: u128, rhs: u128) -> Option<u128> {
+        (self.forward)(lhs, rhs)
+    }
+
+    fn invert(&self, result: u128, rhs: u128) -> Inverse {
+        (self.inverse)(result, rhs)
     }
 }
 
@@ -67,22 +225,146 @@ fn parse_input(input: String) -> Vec<Equation> {
     equations
 }
 
-fn is_possible(acc: u64, parts: &[u64], target: u64) -> bool {
-    (acc == target && parts.is_empty())
-        || ((acc <= target && !parts.is_empty())
-            && (is_possible(Operator::Add.apply(acc, parts[0]), &parts[1..], target)
-                || is_possible(Operator::Mul.apply(acc, parts[0]), &parts[1..], target)
-                || is_possible(Operator::Cat.apply(acc, parts[0]), &parts[1..], target)))
+fn evaluate(parts: &[u128], operators: &[Operator], mode: Mode) -> Option<u128> {
+    match mode {
+        Mode::LeftToRight => operators
+            .iter()
+            .zip(parts[1..].iter())
+            .try_fold(parts[0], |acc, (op, part)| op.checked_apply(acc, *part)),
+        Mode::Precedence => {
+            // Operator precedence parsing. Collapse anything pending that binds tighter (or as
+            // tightly, unless the next operator is right associative) before pushing it.
+            let mut values = vec![parts[0]];
+            let mut pending: Vec<Operator> = Vec::new();
+            fn reduce(values: &mut Vec<u128>, pending: &mut Vec<Operator>) -> Option<()> {
+                let rhs = values.pop().unwrap();
+                let lhs = values.pop().unwrap();
+                values.push(pending.pop().unwrap().checked_apply(lhs, rhs)?);
+                Some(())
+            }
+            for (op, part) in operators.iter().zip(parts[1..].iter()) {
+                while pending.last().is_some_and(|top| {
+                    top.precedence > op.precedence
+                        || (top.precedence == op.precedence && !op.right_associative)
+                }) {
+                    reduce(&mut values, &mut pending)?;
+                }
+                pending.push(*op);
+                values.push(*part);
+            }
+            while !pending.is_empty() {
+                reduce(&mut values, &mut pending)?;
+            }
+            values.pop()
+        }
+    }
+}
+
+/// Every value the parts can make left to right, with the operators that made it.
+fn forward(parts: &[u128], operators: &[Operator]) -> Vec<(u128, Vec<Operator>)> {
+    let mut results = vec![(parts[0], Vec::new())];
+    for part in &parts[1..] {
+        let mut next = Vec::new();
+        for (acc, sequence) in &results {
+            for op in operators {
+                if let Some(value) = op.checked_apply(*acc, *part) {
+                    let mut sequence = sequence.clone();
+                    sequence.push(*op);
+                    next.push((value, sequence));
+                }
+            }
+        }
+        results = next;
+    }
+    results
+}
+
+fn backward(parts: &[u128], target: u128, operators: &[Operator]) -> Vec<Vec<Operator>> {
+    // Working back from the target prunes much harder than going forwards: the last part must
+    // divide it for *, be a suffix of it for ||, fit under it for +, and so on.
+    let Some((last, rest)) = parts.split_last() else {
+        return Vec::new();
+    };
+    if rest.is_empty() {
+        return if *last == target {
+            vec![Vec::new()]
+        } else {
+            Vec::new()
+        };
+    }
+
+    let mut found = Vec::new();
+    for op in operators {
+        let prefixes = match op.invert(target, *last) {
+            Inverse::Impossible => continue,
+            Inverse::Exactly(lhs) => backward(rest, lhs, operators),
+            Inverse::Unknown => forward(rest, operators)
+                .into_iter()
+                .filter(|(lhs, _)| op.checked_apply(*lhs, *last) == Some(target))
+                .map(|(_, sequence)| sequence)
+                .collect(),
+        };
+        for mut sequence in prefixes {
+            sequence.push(*op);
+            found.push(sequence);
+        }
+    }
+    found
+}
+
+/// All operator sequences that make the equation true, sorted by the order operators were given.
+fn solutions(equation: &Equation, operators: &[Operator], mode: Mode) -> Vec<Vec<Operator>> {
+    let mut found = match mode {
+        Mode::LeftToRight => backward(&equation.parts, equation.value, operators),
+        Mode::Precedence => {
+            // Precedence means the last operator isn't necessarily the last one applied, so
+            // there's nothing to prune on, just try every sequence (see Mode::Precedence).
+            let mut sequences: Vec<Vec<Operator>> = vec![Vec::new()];
+            for _ in 1..equation.parts.len() {
+                sequences = sequences
+                    .iter()
+                    .flat_map(|sequence| {
+                        operators.iter().map(|op| {
+                            let mut sequence = sequence.clone();
+                            sequence.push(*op);
+                            sequence
+                        })
+                    })
+                    .collect();
+            }
+            sequences
+                .into_iter()
+                .filter(|sequence| {
+                    evaluate(&equation.parts, sequence, mode) == Some(equation.value)
+                })
+                .collect()
+        }
+    };
+    found.sort_by_key(|sequence| {
+        sequence
+            .iter()
+            .map(|op| operators.iter().position(|o| o == op))
+            .collect::<Vec<_>>()
+    });
+    found
+}
+
+fn render(equation: &Equation, operators: &[Operator]) -> String {
+    let mut s = format!("{} = {}", equation.value, equation.parts[0]);
+    for (op, part) in operators.iter().zip(equation.parts[1..].iter()) {
+        s.push_str(&format!(" {} {}", op.symbol, part));
+    }
+    s
 }
 
-fn solve(parsed: &[Equation]) -> u64 {
+fn solve(parsed: &[Equation]) -> u128 {
     parsed
         .iter()
         .filter_map(|e| {
-            if is_possible(e.parts[0], &e.parts[1..], e.value) {
-                Some(e.value)
-            } else {
+            if solutions(e, &[ADD, MUL, CAT], Mode::LeftToRight).is_empty() {
                 None
+            } else {
+                Some(e.value)
             }
         })
         .sum()
@@ -91,6 +373,14 @@ fn solve(parsed: &[Equation]) -> u64 {
 fn main() {
     let input = load_input(2024, 7);
     let parsed = parse_input(input);
+    if DEBUG {
+        let operators = [ADD, MUL, CAT];
+        for equation in &parsed {
+            for sequence in solutions(equation, &operators, Mode::LeftToRight) {
+                println!("{}", render(equation, &sequence));
+            }
+        }
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -132,10 +422,105 @@ mod tests {
             parsed[parsed.len() - 1]
         );
 
-        assert_eq!(156, Operator::Cat.apply(15, 6));
-        assert_eq!(86, Operator::Cat.apply(8, 6));
-        assert_eq!(178, Operator::Cat.apply(17, 8));
+        assert_eq!(156, CAT.apply(15, 6));
+        assert_eq!(86, CAT.apply(8, 6));
+        assert_eq!(178, CAT.apply(17, 8));
 
         assert_eq!(11387, solve(&parsed));
     }
+
+    #[test]
+    fn day07b_solutions() {
+        let equation = |line: &str| parse_input(line.to_string()).pop().unwrap();
+        let all = [ADD, MUL, CAT];
+
+        assert_eq!(
+            vec![vec![ADD, MUL], vec![MUL, ADD]],
+            solutions(&equation("3267: 81 40 27"), &all, Mode::LeftToRight)
+        );
+        assert_eq!(
+            vec![vec![MUL, CAT, MUL]],
+            solutions(&equation("7290: 6 8 6 15"), &all, Mode::LeftToRight)
+        );
+        assert!(solutions(&equation("83: 17 5"), &all, Mode::LeftToRight).is_empty());
+        assert_eq!(
+            "7290 = 6 * 8 || 6 * 15",
+            render(&equation("7290: 6 8 6 15"), &[MUL, CAT, MUL])
+        );
+
+        // Only 81 * 40 + 27 works when * goes first.
+        assert_eq!(
+            vec![vec![MUL, ADD]],
+            solutions(&equation("3267: 81 40 27"), &all, Mode::Precedence)
+        );
+        assert_eq!(
+            Some(11 + 6 * 16 + 20),
+            evaluate(&[11, 6, 16, 20], &[ADD, MUL, ADD], Mode::Precedence)
+        );
+        assert_eq!(
+            Some(1 + 23),
+            evaluate(&[1, 2, 3], &[ADD, CAT], Mode::Precedence)
+        );
+
+        // Plugging in other operators, % can't be undone so that part is found going forwards.
+        // Powers are undone with a whole number root.
+        assert_eq!(
+            vec![vec![POW, POW]],
+            solutions(&equation("64: 2 3 2"), &[ADD, MUL, POW], Mode::LeftToRight)
+        );
+        assert_eq!(
+            vec![vec![MUL, POW]],
+            solutions(&equation("36: 2 3 2"), &[ADD, MUL, POW], Mode::LeftToRight)
+        );
+        assert!(solutions(&equation("63: 2 3 2"), &[POW], Mode::LeftToRight).is_empty());
+        assert_eq!(
+            vec![vec![ADD, POW], vec![POW, POW]],
+            solutions(&equation("1: 3 4 0"), &[ADD, POW], Mode::LeftToRight)
+        );
+        assert_eq!(
+            vec![vec![POW]],
+            solutions(
+                &equation("340282366920938463463374607431768211455: 340282366920938463463374607431768211455 1"),
+                &[POW],
+                Mode::LeftToRight
+            )
+        );
+        assert_eq!((2, 3), (integer_root(80, 4), integer_root(81, 4)));
+        assert_eq!(
+            Some(2 * 512),
+            evaluate(&[2, 2, 3, 2], &[MUL, POW, POW], Mode::Precedence)
+        );
+        assert_eq!(
+            vec![vec![SUB, SUB], vec![REM, ADD]],
+            solutions(&equation("4: 10 3 3"), &[ADD, SUB, REM], Mode::LeftToRight)
+        );
+        assert!(solutions(&equation("1: 3 10"), &[SUB], Mode::LeftToRight).is_empty());
+
+        // Well past u64.
+        assert_eq!(
+            vec![vec![CAT, CAT]],
+            solutions(
+                &equation("123456789012345678901234567890: 1234567890 1234567890 1234567890"),
+                &all,
+                Mode::LeftToRight
+            )
+        );
+        assert_eq!(
+            vec![vec![MUL, ADD]],
+            solutions(
+                &equation("1000000000000000000000000000001: 1000000000000000 1000000000000000 1"),
+                &all,
+                Mode::LeftToRight
+            )
+        );
+        // 39 digits, so there's no concatenating anything in front of it.
+        assert_eq!(
+            vec![vec![ADD]],
+            solutions(
+                &equation("100000000000000000000000000000000000000: 0 100000000000000000000000000000000000000"),
+                &all,
+                Mode::LeftToRight
+            )
+        );
+    }
 }
@@ -2,6 +2,8 @@ use std::collections::{HashMap, HashSet};
 
 use aoc_2024_rs::*;
 
+const DEBUG: bool = false;
+
 struct State {
     towers: HashMap<char, Vec<Point2<i32>>>,
     bbox: BBox2<i32>,
@@ -44,7 +46,52 @@ fn parse_input(input: String) -> State {
     state
 }
 
-fn get_antinodes(towers: &[Point2<i32>], bbox: &BBox2<i32>) -> Vec<Point2<i32>> {
+// Points are plain coordinate arrays here, so the same antinode logic works for the 2D puzzle
+// grid and for stacks of grids (or anything else).
+type Coord<const N: usize> = [i32; N];
+
+#[derive(Debug, Clone, PartialEq)]
+struct Bounds<const N: usize> {
+    min: Coord<N>,
+    max: Coord<N>,
+}
+
+impl<const N: usize> Bounds<N> {
+    fn contains(&self, p: &Coord<N>) -> bool {
+        (0..N).all(|i| self.min[i] <= p[i] && p[i] <= self.max[i])
+    }
+}
+
+impl From<&BBox2<i32>> for Bounds<2> {
+    fn from(bbox: &BBox2<i32>) -> Self {
+        Self {
+            min: [bbox.min.x, bbox.min.y],
+            max: [bbox.max.x, bbox.max.y],
+        }
+    }
+}
+
+fn to_coord(p: &Point2<i32>) -> Coord<2> {
+    [p.x, p.y]
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[allow(dead_code)]
+enum Harmonic {
+    // Part 1: one tower is twice as far away as the other, outside the pair.
+    Double,
+    // Also twice as far, but between the pair, 1/3 and 2/3 of the way along. Only possible when
+    // those land exactly on the grid.
+    Thirds,
+    // Part 2: every grid point on the line, which includes the towers themselves.
+    All,
+}
+
+fn get_antinodes<const N: usize>(
+    towers: &[Coord<N>],
+    bounds: &Bounds<N>,
+    rules: &[Harmonic],
+) -> Vec<Coord<N>> {
     let mut antinodes = Vec::new();
 
     // .a.
@@ -54,57 +101,125 @@ fn get_antinodes(towers: &[Point2<i32>], bbox: &BBox2<i32>) -> Vec<Point2<i32>>
     // ..a
     // .a.
     // rise=1, run=-1
+    //
+    // Generalised to delta, one component per dimension.
+
+    let along = |p: &Coord<N>, d: &Coord<N>, k: i32| -> Coord<N> {
+        let mut q = *p;
+        for i in 0..N {
+            q[i] += d[i] * k;
+        }
+        q
+    };
 
     for i in 0..towers.len() {
         for j in (i + 1)..towers.len() {
             let a = towers[i];
             let b = towers[j];
 
-            let rise = b.y - a.y;
-            let run = b.x - a.x;
+            let mut delta = [0; N];
+            for (n, d) in delta.iter_mut().enumerate() {
+                *d = b[n] - a[n];
+            }
+            // Two towers in the same spot don't define a line.
+            if delta.iter().all(|d| *d == 0) {
+                continue;
+            }
 
-            let mut updated;
-            for step in 1.. {
-                updated = false;
-                let ca = Point2::new(a.x - run * step, a.y - rise * step);
-                if bbox.contains(&ca) {
-                    antinodes.push(ca);
-                    updated = true;
-                }
-                let cb = Point2::new(b.x + run * step, b.y + rise * step);
-                if bbox.contains(&cb) {
-                    antinodes.push(cb);
-                    updated = true;
-                }
-                if !updated {
-                    break;
+            for rule in rules {
+                match rule {
+                    Harmonic::Double => {
+                        antinodes.push(along(&a, &delta, -1));
+                        antinodes.push(along(&b, &delta, 1));
+                    }
+                    Harmonic::Thirds => {
+                        if delta.iter().all(|d| d % 3 == 0) {
+                            let third = delta.map(|d| d / 3);
+                            antinodes.push(along(&a, &third, 1));
+                            antinodes.push(along(&a, &third, 2));
+                        }
+                    }
+                    Harmonic::All => {
+                        // Reduce the step, otherwise a pair like (0,0) and (2,4) would skip
+                        // right over (1,2).
+                        let divisor = delta.iter().fold(0, |acc, d| gcd(acc, *d)).abs();
+                        let step = delta.map(|d| d / divisor);
+                        for direction in [1, -1] {
+                            for k in 0.. {
+                                let p = along(&a, &step, k * direction);
+                                if !bounds.contains(&p) {
+                                    break;
+                                }
+                                antinodes.push(p);
+                            }
+                        }
+                    }
                 }
             }
         }
     }
 
+    antinodes.retain(|p| bounds.contains(p));
+    antinodes.sort();
+    antinodes.dedup();
     antinodes
 }
 
-fn solve(parsed: State) -> usize {
+fn get_all_antinodes(parsed: &State, rules: &[Harmonic]) -> HashSet<Coord<2>> {
+    let bounds = Bounds::from(&parsed.bbox);
     let mut antinodes = HashSet::new();
-    for (_, towers) in parsed.towers {
-        for antinode in get_antinodes(&towers, &parsed.bbox) {
-            antinodes.insert(antinode);
-        }
-        if towers.len() > 1 {
-            for tower in towers {
-                antinodes.insert(tower);
-            }
+    for towers in parsed.towers.values() {
+        let towers: Vec<Coord<2>> = towers.iter().map(to_coord).collect();
+        antinodes.extend(get_antinodes(&towers, &bounds, rules));
+    }
+    antinodes
+}
+
+/// The grid with only this frequency's towers and their antinodes ('#' unless a tower is there).
+fn render_frequency(parsed: &State, frequency: char, rules: &[Harmonic]) -> String {
+    let towers: Vec<Coord<2>> = match parsed.towers.get(&frequency) {
+        Some(towers) => towers.iter().map(to_coord).collect(),
+        None => Vec::new(),
+    };
+    let antinodes: HashSet<Coord<2>> = get_antinodes(&towers, &Bounds::from(&parsed.bbox), rules)
+        .into_iter()
+        .collect();
+
+    let mut lines = Vec::new();
+    for y in parsed.bbox.min.y..=parsed.bbox.max.y {
+        let mut line = String::new();
+        for x in parsed.bbox.min.x..=parsed.bbox.max.x {
+            line.push(if towers.contains(&[x, y]) {
+                frequency
+            } else if antinodes.contains(&[x, y]) {
+                '#'
+            } else {
+                '.'
+            });
         }
+        lines.push(line);
     }
+    lines.join("\n")
+}
 
-    antinodes.len()
+fn solve(parsed: State) -> usize {
+    get_all_antinodes(&parsed, &[Harmonic::All]).len()
 }
 
 fn main() {
     let input = load_input(2024, 8);
     let parsed = parse_input(input);
+    if DEBUG {
+        let mut frequencies: Vec<&char> = parsed.towers.keys().collect();
+        frequencies.sort();
+        for frequency in frequencies {
+            println!("{}:", frequency);
+            println!(
+                "{}",
+                render_frequency(&parsed, *frequency, &[Harmonic::All])
+            );
+        }
+    }
     let answer = solve(parsed);
     println!("Answer: {:?}", answer);
 }
@@ -183,4 +298,78 @@ T....#....
 
         assert_eq!(9, solve(parsed));
     }
+
+    #[test]
+    fn day08b_harmonics() {
+        let input = "
+..........
+...#......
+..........
+....a.....
+..........
+.....a....
+..........
+......#...
+..........
+..........
+        "
+        .trim()
+        .to_string();
+        let mut parsed = parse_input(input);
+
+        parsed.towers.remove(&'#');
+
+        assert_eq!(
+            "
+..........
+...#......
+..........
+....a.....
+..........
+.....a....
+..........
+......#...
+..........
+..........
+            "
+            .trim(),
+            render_frequency(&parsed, 'a', &[Harmonic::Double])
+        );
+        assert_eq!(2, get_all_antinodes(&parsed, &[Harmonic::Double]).len());
+        assert_eq!(5, get_all_antinodes(&parsed, &[Harmonic::All]).len());
+
+        // Only the gcd reduced step finds the points between (and past) these two.
+        let bounds = Bounds {
+            min: [0, 0],
+            max: [6, 6],
+        };
+        assert_eq!(
+            vec![[0, 0], [1, 2], [2, 4], [3, 6]],
+            get_antinodes(&[[0, 0], [2, 4]], &bounds, &[Harmonic::All])
+        );
+        assert_eq!(
+            vec![[1, 1], [2, 2]],
+            get_antinodes(&[[0, 0], [3, 3]], &bounds, &[Harmonic::Thirds])
+        );
+        assert!(get_antinodes(&[[0, 0], [2, 4]], &bounds, &[Harmonic::Thirds]).is_empty());
+        assert!(get_antinodes(&[[1, 1], [1, 1]], &bounds, &[Harmonic::All]).is_empty());
+
+        // Same rules in 3D, on a 4x4x4 cube.
+        let bounds = Bounds {
+            min: [0, 0, 0],
+            max: [3, 3, 3],
+        };
+        assert_eq!(
+            vec![[0, 0, 0], [3, 3, 3]],
+            get_antinodes(&[[1, 1, 1], [2, 2, 2]], &bounds, &[Harmonic::Double])
+        );
+        assert_eq!(
+            vec![[0, 0, 0], [1, 1, 1], [2, 2, 2], [3, 3, 3]],
+            get_antinodes(&[[0, 0, 0], [3, 3, 3]], &bounds, &[Harmonic::All])
+        );
+        assert_eq!(
+            vec![[1, 1, 0], [2, 2, 0]],
+            get_antinodes(&[[0, 0, 0], [3, 3, 0]], &bounds, &[Harmonic::Thirds])
+        );
+    }
 }
@@ -78,22 +78,37 @@ fn get_trailheads(state: &State) -> HashSet<(Point2<i32>, Point2<i32>)> {
         }
     }
 
-    let mut pairs = HashSet::new();
-    for start in starts {
-        let mut visited = HashSet::new();
-        let mut unvisited = vec![start];
-        while let Some(at) = unvisited.pop() {
-            if visited.contains(&at) {
+    // Rather than walking every path from every start, go a height layer at a time from the top
+    // down, with each cell keeping a bitset of which ends it can reach (the union of its uphill
+    // neighbours'). Every start then has its whole answer in one sweep.
+    let words = ends.len().div_ceil(64);
+    let mut reachable: HashMap<Point2<i32>, Vec<u64>> = HashMap::new();
+    for (id, end) in ends.iter().enumerate() {
+        let mut bits = vec![0; words];
+        bits[id / 64] |= 1 << (id % 64);
+        reachable.insert(*end, bits);
+    }
+    for height in (0..9).rev() {
+        for (p, v) in &state.grid {
+            if *v != height {
                 continue;
             }
-            visited.insert(at);
-            for n in get_neighbors(state, &at) {
-                if ends.contains(&n) {
-                    pairs.insert((start, n));
-                } else {
-                    unvisited.push(n);
+            let mut bits = vec![0; words];
+            for n in get_neighbors(state, p) {
+                for (word, other) in bits.iter_mut().zip(reachable[&n].iter()) {
+                    *word |= other;
                 }
             }
+            reachable.insert(*p, bits);
+        }
+    }
+
+    let mut pairs = HashSet::new();
+    for start in starts {
+        for (id, end) in ends.iter().enumerate() {
+            if reachable[&start][id / 64] & (1 << (id % 64)) != 0 {
+                pairs.insert((start, *end));
+            }
         }
     }
 
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::{collections::HashMap, ops::RangeInclusive};
 
 use aoc_2024_rs::*;
 
@@ -30,7 +30,8 @@ fn parse_input(input: String) -> State {
                 '.' => {}
                 c => {
                     let p = Point2::new(x.try_into().unwrap(), y.try_into().unwrap());
-                    state.grid.insert(p, c.to_digit(10).unwrap());
+                    // Base 36 so maps can go higher than 9 (a is 10, up to z for 35).
+                    state.grid.insert(p, c.to_digit(36).unwrap());
                     state.bbox.update(&p);
                 }
             }
@@ -40,7 +41,35 @@ fn parse_input(input: String) -> State {
     state
 }
 
-fn get_neighbors(state: &State, at: &Point2<i32>) -> Vec<Point2<i32>> {
+#[derive(Debug, Clone, PartialEq)]
+struct Rules {
+    // Trails start on this height and finish on the end height.
+    start: u32,
+    end: u32,
+    // How much higher each step can be. Has to be uphill, so no 0, otherwise a trail could go
+    // round in circles.
+    step: RangeInclusive<u32>,
+}
+
+impl Default for Rules {
+    fn default() -> Self {
+        Self {
+            start: 0,
+            end: 9,
+            step: 1..=1,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Cell {
+    // How many distinct ends can be reached from here.
+    score: usize,
+    // How many distinct trails lead from here to any end.
+    rating: u64,
+}
+
+fn get_neighbors(state: &State, rules: &Rules, at: &Point2<i32>) -> Vec<Point2<i32>> {
     let v = state.grid.get(at).unwrap();
 
     let steps = [
@@ -53,7 +82,7 @@ fn get_neighbors(state: &State, at: &Point2<i32>) -> Vec<Point2<i32>> {
     let mut neighbors = Vec::new();
     for step in steps {
         if let Some(step_v) = state.grid.get(&step) {
-            if step_v > v && step_v - v == 1 {
+            if step_v > v && rules.step.contains(&(step_v - v)) && *step_v <= rules.end {
                 neighbors.push(step);
             }
         }
@@ -62,40 +91,99 @@ fn get_neighbors(state: &State, at: &Point2<i32>) -> Vec<Point2<i32>> {
     neighbors
 }
 
-fn get_trailheads(state: &State) -> HashMap<Point2<i32>, usize> {
-    let mut starts = Vec::new();
-    let mut ends = Vec::new();
-    for y in state.bbox.min.y..=state.bbox.max.y {
-        for x in state.bbox.min.x..=state.bbox.max.x {
-            let p = Point2::new(x, y);
-            if let Some(v) = state.grid.get(&p) {
-                match v {
-                    0 => starts.push(p),
-                    9 => ends.push(p),
-                    _ => {}
+fn get_cells(state: &State, rules: &Rules) -> HashMap<Point2<i32>, Cell> {
+    // Since every step goes uphill, working down from the highest layer means every neighbour a
+    // cell could step to has already been done. Ratings just add up. Scores need to know which
+    // ends are reachable (two routes to the same end only count once), so each cell carries a
+    // bitset of end ids.
+    assert!(!rules.step.contains(&0), "Steps have to go uphill.");
+
+    let mut cells: Vec<(u32, Point2<i32>)> = state
+        .grid
+        .iter()
+        .filter(|(_, v)| rules.start <= **v && **v <= rules.end)
+        .map(|(p, v)| (*v, *p))
+        .collect();
+    cells.sort_by(|a, b| b.cmp(a));
+
+    let mut end_ids = HashMap::new();
+    for (v, p) in &cells {
+        if *v == rules.end {
+            end_ids.insert(*p, end_ids.len());
+        }
+    }
+    let words = end_ids.len().div_ceil(64);
+
+    let mut reachable: HashMap<Point2<i32>, Vec<u64>> = HashMap::new();
+    let mut result = HashMap::new();
+    for (v, p) in &cells {
+        let mut ends = vec![0; words];
+        let mut rating = 0;
+        if *v == rules.end {
+            let id = end_ids[p];
+            ends[id / 64] |= 1 << (id % 64);
+            rating = 1;
+        } else {
+            for n in get_neighbors(state, rules, p) {
+                for (word, other) in ends.iter_mut().zip(reachable[&n].iter()) {
+                    *word |= other;
                 }
+                rating += result.get(&n).map_or(0, |c: &Cell| c.rating);
             }
         }
+        let score = ends.iter().map(|w| w.count_ones() as usize).sum();
+        reachable.insert(*p, ends);
+        result.insert(*p, Cell { score, rating });
     }
 
-    let mut pairs = HashMap::new();
-    for start in starts {
-        let mut unvisited = vec![start];
-        while let Some(at) = unvisited.pop() {
-            for n in get_neighbors(state, &at) {
-                if ends.contains(&n) {
-                    pairs
-                        .entry(start)
-                        .and_modify(|count| *count += 1)
-                        .or_insert(1);
-                } else {
-                    unvisited.push(n);
-                }
+    result
+}
+
+fn get_trailheads_with(state: &State, rules: &Rules) -> HashMap<Point2<i32>, Cell> {
+    get_cells(state, rules)
+        .into_iter()
+        .filter(|(p, cell)| state.grid[p] == rules.start && cell.rating > 0)
+        .collect()
+}
+
+fn get_trailheads(state: &State) -> HashMap<Point2<i32>, usize> {
+    get_trailheads_with(state, &Rules::default())
+        .into_iter()
+        .map(|(p, cell)| (p, usize::try_from(cell.rating).unwrap()))
+        .collect()
+}
+
+/// Every trail from this point to an end, each as the list of points along it.
+#[allow(dead_code)]
+fn list_trails(state: &State, rules: &Rules, from: &Point2<i32>) -> Vec<Vec<Point2<i32>>> {
+    // Only ever step onto cells with a rating, so no time is wasted on dead ends.
+    fn inner(
+        state: &State,
+        rules: &Rules,
+        cells: &HashMap<Point2<i32>, Cell>,
+        trail: &mut Vec<Point2<i32>>,
+        trails: &mut Vec<Vec<Point2<i32>>>,
+    ) {
+        let at = *trail.last().unwrap();
+        if state.grid[&at] == rules.end {
+            trails.push(trail.clone());
+            return;
+        }
+        for n in get_neighbors(state, rules, &at) {
+            if cells.get(&n).is_some_and(|c| c.rating > 0) {
+                trail.push(n);
+                inner(state, rules, cells, trail, trails);
+                trail.pop();
             }
         }
     }
 
-    pairs
+    let cells = get_cells(state, rules);
+    let mut trails = Vec::new();
+    if cells.get(from).is_some_and(|c| c.rating > 0) {
+        inner(state, rules, &cells, &mut vec![*from], &mut trails);
+    }
+    trails
 }
 
 fn solve(parsed: &State) -> usize {
@@ -193,4 +281,77 @@ mod tests {
 
         assert_eq!(81, solve(&parsed));
     }
+
+    #[test]
+    fn day10b_cells_and_trails() {
+        let input = "
+89010123
+78121874
+87430965
+96549874
+45678903
+32019012
+01329801
+10456732
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+        let rules = Rules::default();
+
+        let trailheads = get_trailheads_with(&parsed, &rules);
+        assert_eq!(9, trailheads.len());
+        assert_eq!(36, trailheads.values().map(|c| c.score).sum::<usize>());
+        assert_eq!(81, trailheads.values().map(|c| c.rating).sum::<u64>());
+        assert_eq!(
+            Cell {
+                score: 5,
+                rating: 20
+            },
+            trailheads[&Point2::new(2, 0)]
+        );
+
+        let trails = list_trails(&parsed, &rules, &Point2::new(2, 0));
+        assert_eq!(20, trails.len());
+        for trail in &trails {
+            assert_eq!(10, trail.len());
+            let heights: Vec<u32> = trail.iter().map(|p| parsed.grid[p]).collect();
+            assert_eq!((0..=9).collect::<Vec<u32>>(), heights);
+        }
+        // Starting part way up works too.
+        assert_eq!(
+            vec![vec![Point2::new(0, 0), Point2::new(1, 0)]],
+            list_trails(&parsed, &rules, &Point2::new(0, 0))
+        );
+
+        // Heights past 9, and steps of 2 or 3 at a time.
+        let parsed = parse_input(
+            "
+02468ac
+.......
+0369cfi
+            "
+            .trim()
+            .to_string(),
+        );
+        let rules = Rules {
+            start: 0,
+            end: 12,
+            step: 2..=3,
+        };
+        let cells = get_cells(&parsed, &rules);
+        assert_eq!(1, cells[&Point2::new(0, 0)].rating);
+        assert_eq!(1, cells[&Point2::new(0, 2)].rating);
+        assert_eq!(
+            vec![vec![
+                Point2::new(0, 2),
+                Point2::new(1, 2),
+                Point2::new(2, 2),
+                Point2::new(3, 2),
+                Point2::new(4, 2),
+            ]],
+            list_trails(&parsed, &rules, &Point2::new(0, 2))
+        );
+        assert!(!cells.contains_key(&Point2::new(5, 2)));
+    }
 }
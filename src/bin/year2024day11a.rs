@@ -17,7 +17,7 @@ fn count_stones(stone: u64, depth: u64) -> u64 {
     }
 
     let digits = stone.ilog10() + 1;
-    if digits % 2 == 0 {
+    if digits.is_multiple_of(2) {
         let lhs = stone / 10u64.pow(digits / 2);
         let rhs = stone % 10u64.pow(digits / 2);
         return count_stones(lhs, depth - 1) + count_stones(rhs, depth - 1);
@@ -1,5 +1,3 @@
-use std::collections::HashMap;
-
 use aoc_2024_rs::*;
 
 fn parse_input(input: String) -> Vec<u64> {
@@ -10,41 +8,32 @@ fn parse_input(input: String) -> Vec<u64> {
 }
 
 fn count_stones(stones: &[u64], depth: u64) -> u64 {
-    fn inner(stone: u64, depth: u64, cache: &mut HashMap<(u64, u64), u64>) -> u64 {
+    count_stones_with(stones, depth, &mut Memo::new())
+}
+
+fn count_stones_with(stones: &[u64], depth: u64, memo: &mut Memo<(u64, u64), u64>) -> u64 {
+    fn inner(stone: u64, depth: u64, memo: &mut Memo<(u64, u64), u64>) -> u64 {
         if depth == 0 {
             return 1;
         }
 
-        let key = (stone, depth);
-        if let Some(value) = cache.get(&key) {
-            return *value;
-        }
-
-        if stone == 0 {
-            let v = inner(1, depth - 1, cache);
-            cache.insert(key, v);
-            return v;
-        }
+        memo.get_or_compute((stone, depth), |memo| {
+            if stone == 0 {
+                return inner(1, depth - 1, memo);
+            }
 
-        let digits = stone.ilog10() + 1;
-        if digits % 2 == 0 {
-            let lhs = stone / 10u64.pow(digits / 2);
-            let rhs = stone % 10u64.pow(digits / 2);
-            let v = inner(lhs, depth - 1, cache) + inner(rhs, depth - 1, cache);
-            cache.insert(key, v);
-            return v;
-        }
+            let digits = stone.ilog10() + 1;
+            if digits.is_multiple_of(2) {
+                let lhs = stone / 10u64.pow(digits / 2);
+                let rhs = stone % 10u64.pow(digits / 2);
+                return inner(lhs, depth - 1, memo) + inner(rhs, depth - 1, memo);
+            }
 
-        let v = inner(stone * 2024, depth - 1, cache);
-        cache.insert(key, v);
-        v
+            inner(stone * 2024, depth - 1, memo)
+        })
     }
 
-    let mut cache = HashMap::new();
-    stones
-        .iter()
-        .map(|&stone| inner(stone, depth, &mut cache))
-        .sum()
+    stones.iter().map(|&stone| inner(stone, depth, memo)).sum()
 }
 
 fn solve(parsed: &[u64], depth: u64) -> u64 {
@@ -86,4 +75,20 @@ mod tests {
         assert_eq!(22, solve(&parsed, 6));
         assert_eq!(55312, solve(&parsed, 25));
     }
+
+    #[test]
+    fn day11b_memo_stats() {
+        let parsed = parse_input("125 17".to_string());
+
+        let mut memo = Memo::new();
+        assert_eq!(55312, count_stones_with(&parsed, 25, &mut memo));
+        assert_eq!(memo.misses(), memo.len());
+        assert!(memo.hits() > 0);
+
+        // Asking again is all hits.
+        let (hits, misses) = (memo.hits(), memo.misses());
+        assert_eq!(55312, count_stones_with(&parsed, 25, &mut memo));
+        assert_eq!(hits + parsed.len(), memo.hits());
+        assert_eq!(misses, memo.misses());
+    }
 }
@@ -169,7 +169,7 @@ p=9,5 v=-3,-3
 
         assert_eq!(
             (Point2::new(0, 4), Point2::new(3, -3)),
-            *parsed.grid.get(0).unwrap()
+            *parsed.grid.first().unwrap()
         );
         assert_eq!(
             (Point2::new(6, 3), Point2::new(-1, -3)),
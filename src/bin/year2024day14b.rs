@@ -1,9 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_2024_rs::*;
 use regex::Regex;
 
 const DEBUG: bool = false;
+const EXPORT_STATS: bool = false;
 
 #[derive(Debug, PartialEq, Clone)]
 struct State {
@@ -82,6 +83,140 @@ fn tick(state: &State) -> State {
     next
 }
 
+fn position_at(p: &Point2<i32>, v: &Point2<i32>, bbox: &BBox2<i32>, t: usize) -> Point2<i32> {
+    // No need to tick through every second, it's all just modular arithmetic. Reduce t first so
+    // that v*t can't overflow for big t.
+    let w = bbox.max.x + 1;
+    let h = bbox.max.y + 1;
+    let tx = (t % w as usize) as i32;
+    let ty = (t % h as usize) as i32;
+    Point2::new(
+        (p.x + v.x.rem_euclid(w) * tx).rem_euclid(w),
+        (p.y + v.y.rem_euclid(h) * ty).rem_euclid(h),
+    )
+}
+
+fn positions_at(state: &State, t: usize) -> Vec<Point2<i32>> {
+    state
+        .grid
+        .iter()
+        .map(|(p, v)| position_at(p, v, &state.bbox, t))
+        .collect()
+}
+
+fn period(bbox: &BBox2<i32>) -> usize {
+    // Every robot is back where it started after lcm(W, H) seconds. When the dimensions are
+    // coprime (as in the puzzle) that's just W*H.
+    let w = (bbox.max.x + 1) as usize;
+    let h = (bbox.max.y + 1) as usize;
+    w / gcd(w, h) * h
+}
+
+fn safety_factor(positions: &[Point2<i32>], bbox: &BBox2<i32>) -> usize {
+    let mid = Point2::new(bbox.max.x / 2, bbox.max.y / 2);
+
+    let mut counts = [0; 4];
+    for p in positions {
+        if p.x == mid.x || p.y == mid.y {
+            continue;
+        }
+        let i = match (p.x < mid.x, p.y < mid.y) {
+            (true, true) => 0,
+            (false, true) => 1,
+            (true, false) => 2,
+            (false, false) => 3,
+        };
+        counts[i] += 1;
+    }
+
+    counts.iter().product()
+}
+
+fn spatial_variance(positions: &[Point2<i32>]) -> f64 {
+    if positions.is_empty() {
+        return 0.0;
+    }
+
+    let n = positions.len() as f64;
+    let mean_x = positions.iter().map(|p| p.x as f64).sum::<f64>() / n;
+    let mean_y = positions.iter().map(|p| p.y as f64).sum::<f64>() / n;
+
+    positions
+        .iter()
+        .map(|p| (p.x as f64 - mean_x).powi(2) + (p.y as f64 - mean_y).powi(2))
+        .sum::<f64>()
+        / n
+}
+
+fn largest_cluster(positions: &[Point2<i32>]) -> usize {
+    // Size is counted in robots, not cells, so stacked robots all count towards their cluster.
+    let mut counts: HashMap<Point2<i32>, usize> = HashMap::with_capacity(positions.len());
+    for p in positions {
+        counts.entry(*p).and_modify(|c| *c += 1).or_insert(1);
+    }
+
+    let mut seen: HashSet<Point2<i32>> = HashSet::with_capacity(counts.len());
+    let mut largest = 0;
+    for start in counts.keys() {
+        if !seen.insert(*start) {
+            continue;
+        }
+
+        let mut size = 0;
+        let mut queue = VecDeque::from([*start]);
+        while let Some(p) = queue.pop_front() {
+            size += counts[&p];
+            for direction in [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ] {
+                let q = direction.step(&p);
+                if counts.contains_key(&q) && seen.insert(q) {
+                    queue.push_back(q);
+                }
+            }
+        }
+        largest = largest.max(size);
+    }
+
+    largest
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct TickStats {
+    t: usize,
+    safety_factor: usize,
+    variance: f64,
+    largest_cluster: usize,
+}
+
+fn get_stats(state: &State) -> Vec<TickStats> {
+    (0..period(&state.bbox))
+        .map(|t| {
+            let positions = positions_at(state, t);
+            TickStats {
+                t,
+                safety_factor: safety_factor(&positions, &state.bbox),
+                variance: spatial_variance(&positions),
+                largest_cluster: largest_cluster(&positions),
+            }
+        })
+        .collect()
+}
+
+fn stats_to_csv(stats: &[TickStats]) -> String {
+    let mut csv = String::from("t,safety_factor,variance,largest_cluster\n");
+    for s in stats {
+        csv.push_str(&format!(
+            "{},{},{},{}\n",
+            s.t, s.safety_factor, s.variance, s.largest_cluster
+        ));
+    }
+    csv
+}
+
 fn longest_continuous_column(state: &State, x: i32) -> usize {
     let mut ys: Vec<i32> = state
         .grid
@@ -149,6 +284,10 @@ fn solve(parsed: &State) -> usize {
 fn main() {
     let input = load_input(2024, 14);
     let parsed = parse_input(input);
+    if EXPORT_STATS {
+        let csv = stats_to_csv(&get_stats(&parsed));
+        std::fs::write("year2024day14b-stats.csv", csv).expect("Write stats failed!");
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -184,7 +323,7 @@ p=9,5 v=-3,-3
 
         assert_eq!(
             (Point2::new(0, 4), Point2::new(3, -3)),
-            *parsed.grid.get(0).unwrap()
+            *parsed.grid.first().unwrap()
         );
         assert_eq!(
             (Point2::new(6, 3), Point2::new(-1, -3)),
@@ -228,4 +367,71 @@ p=2,4 v=2,-3
         state = tick(&state);
         assert_eq!(vec![(Point2::new(1, 3), Point2::new(2, -3))], state.grid);
     }
+
+    #[test]
+    fn day14b_closed_form() {
+        let input = "
+p=0,4 v=3,-3
+p=6,3 v=-1,-3
+p=10,3 v=-1,2
+p=2,0 v=2,-1
+p=0,0 v=1,3
+p=3,0 v=-2,-2
+p=7,6 v=-1,-3
+p=3,0 v=-1,-2
+p=9,3 v=2,3
+p=7,3 v=-1,2
+p=2,4 v=2,-3
+p=9,5 v=-3,-3
+        "
+        .trim()
+        .to_string();
+        let mut state = parse_input(input);
+        state.bbox.min = Point2::new(0, 0);
+        state.bbox.max = Point2::new(10, 6);
+
+        let mut ticked = state.clone();
+        for t in 0..100 {
+            let expected: Vec<Point2<i32>> = ticked.grid.iter().map(|(p, _)| *p).collect();
+            assert_eq!(expected, positions_at(&state, t));
+            ticked = tick(&ticked);
+        }
+
+        assert_eq!(77, period(&state.bbox));
+        assert_eq!(positions_at(&state, 3), positions_at(&state, 3 + 77));
+        assert_eq!(12, safety_factor(&positions_at(&state, 100), &state.bbox));
+
+        let stats = get_stats(&state);
+        assert_eq!(77, stats.len());
+        assert_eq!(12, stats[100 % 77].safety_factor);
+
+        let csv = stats_to_csv(&stats);
+        assert_eq!(78, csv.lines().count());
+        assert_eq!(
+            "t,safety_factor,variance,largest_cluster",
+            csv.lines().next().unwrap()
+        );
+    }
+
+    #[test]
+    fn day14b_cluster_and_variance() {
+        let positions = vec![
+            Point2::new(0, 0),
+            Point2::new(1, 0),
+            Point2::new(1, 0),
+            Point2::new(1, 1),
+            Point2::new(5, 5),
+        ];
+        assert_eq!(4, largest_cluster(&positions));
+        assert_eq!(0, largest_cluster(&[]));
+
+        assert_eq!(
+            0.0,
+            spatial_variance(&[Point2::new(3, 3), Point2::new(3, 3)])
+        );
+        assert_eq!(
+            1.0,
+            spatial_variance(&[Point2::new(0, 0), Point2::new(2, 0)])
+        );
+    }
 }
@@ -1,19 +1,26 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 
 use aoc_2024_rs::*;
 
 const DEBUG: bool = false;
+const EXPORT_JOURNAL: bool = false;
+// Print the warehouse as it was after this many moves.
+const SHOW_STEP: Option<usize> = None;
+// Plan the shortest moves to push this box (by id) so its top left ends up at (x, y).
+const PLAN: Option<(usize, (i32, i32))> = None;
 
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 enum Tile {
     Open,
     Wall,
-    Box,
+    // Every cell a box covers points back at it, so a box can be any rectangle.
+    Box(usize),
 }
 
 #[derive(Debug, PartialEq, Clone)]
 struct State {
     grid: HashMap<Point2<i32>, Tile>,
+    boxes: Vec<BBox2<i32>>,
     bbox: BBox2<i32>,
     bot: Point2<i32>,
     movements: Vec<Direction>,
@@ -23,14 +30,40 @@ impl State {
     fn new() -> Self {
         Self {
             grid: HashMap::new(),
+            boxes: Vec::new(),
             bbox: BBox2::default(),
             bot: Point2::min(),
             movements: Vec::new(),
         }
     }
+
+    fn add_box(&mut self, b: BBox2<i32>) -> usize {
+        let id = self.boxes.len();
+        for p in cells(&b) {
+            self.grid.insert(p, Tile::Box(id));
+        }
+        self.boxes.push(b);
+        id
+    }
+}
+
+fn cells(b: &BBox2<i32>) -> Vec<Point2<i32>> {
+    let mut cells = Vec::new();
+    for y in b.min.y..=b.max.y {
+        for x in b.min.x..=b.max.x {
+            cells.push(Point2::new(x, y));
+        }
+    }
+    cells
 }
 
 fn parse_input(input: String) -> State {
+    parse_input_scaled(input, 2)
+}
+
+fn parse_input_scaled(input: String, scale: i32) -> State {
+    // A scale of 1 gives the part a warehouse, 2 gives this one. Anything else (or a mix of box
+    // sizes) only needs to call add_box with a different rectangle.
     let mut state = State::new();
 
     let grid_chars = HashSet::from(['#', '.', 'O', '@']);
@@ -47,27 +80,21 @@ fn parse_input(input: String) -> State {
         let line_chars = HashSet::from_iter(line.chars());
         if line_chars.is_subset(&grid_chars) {
             for ch in line.chars() {
-                let p = Point2::new(x, y);
-                let mut wide = true;
-                if let Some(t) = match ch {
-                    '#' => Some(Tile::Wall),
-                    '.' => Some(Tile::Open),
+                let p = Point2::new(x * scale, y);
+                let t = match ch {
+                    '#' => Tile::Wall,
+                    '.' | 'O' | '@' => Tile::Open,
+                    _ => panic!("Unknown grid char: {:?}", ch),
+                };
+                for dx in 0..scale {
+                    state.grid.insert(Point2::new(p.x + dx, y), t);
+                }
+                match ch {
                     'O' => {
-                        wide = false;
-                        Some(Tile::Box)
-                    }
-                    '@' => {
-                        wide = false;
-                        state.bot = p;
-                        Some(Tile::Open)
+                        state.add_box(BBox2::new(&p, &Point2::new(p.x + scale - 1, y)));
                     }
-                    _ => panic!("Unknown grid char: {:?}", ch),
-                } {
-                    state.grid.insert(p, t);
-                    x += 1;
-                    state
-                        .grid
-                        .insert(Point2::new(x, y), if wide { t } else { Tile::Open });
+                    '@' => state.bot = p,
+                    _ => {}
                 }
                 state.bbox.update(&p);
                 x += 1;
@@ -76,14 +103,7 @@ fn parse_input(input: String) -> State {
             y += 1;
         } else if line_chars.is_subset(&dir_chars) {
             for ch in line.chars() {
-                let d = match ch {
-                    '^' => Direction::North,
-                    '>' => Direction::East,
-                    'v' => Direction::South,
-                    '<' => Direction::West,
-                    _ => panic!("Unknown movement char: {:?}", ch),
-                };
-                state.movements.push(d);
+                state.movements.push(char_to_direction(ch));
             }
         } else {
             panic!("Unparsed line: {:?}", line);
@@ -93,14 +113,6 @@ fn parse_input(input: String) -> State {
     state
 }
 
-fn is_left_box(state: &State, p: &Point2<i32>) -> bool {
-    state.grid.get(p) == Some(&Tile::Box)
-}
-
-fn is_right_box(state: &State, p: &Point2<i32>) -> bool {
-    state.grid.get(p) == Some(&Tile::Open) && is_left_box(state, &Direction::West.step(p))
-}
-
 #[allow(dead_code)]
 fn pprint_grid(state: &State) {
     for y in state.bbox.min.y..=state.bbox.max.y {
@@ -110,15 +122,20 @@ fn pprint_grid(state: &State) {
                 '@'
             } else {
                 match state.grid.get(&p) {
-                    Some(Tile::Open) => {
-                        if is_right_box(state, &p) {
+                    Some(Tile::Open) => '.',
+                    Some(Tile::Wall) => '#',
+                    Some(Tile::Box(id)) => {
+                        let b = &state.boxes[*id];
+                        if b.min.x == b.max.x {
+                            'O'
+                        } else if p.x == b.min.x {
+                            '['
+                        } else if p.x == b.max.x {
                             ']'
                         } else {
-                            '.'
+                            '='
                         }
                     }
-                    Some(Tile::Wall) => '#',
-                    Some(Tile::Box) => '[',
                     None => unreachable!(),
                 }
             };
@@ -143,25 +160,32 @@ fn pprint_grid(state: &State) {
     );
 }
 
-fn step_horizontally(state: &State, at: &Point2<i32>, d: &Direction) -> Option<Vec<Point2<i32>>> {
+fn find_pushed(state: &State, d: &Direction) -> Option<Vec<usize>> {
+    // Flood out from the bot in the direction of travel. Every box touched is added to the group,
+    // and every cell its leading face would move into is checked next. A single wall anywhere
+    // blocks the whole move.
     let mut group = Vec::new();
+    let mut seen = HashSet::new();
 
-    let mut step = d.step(at);
-    while let Some(t) = state.grid.get(&step) {
-        match t {
-            Tile::Wall => {
+    let mut frontier = vec![d.step(&state.bot)];
+    while let Some(p) = frontier.pop() {
+        match state.grid.get(&p) {
+            Some(Tile::Open) => {}
+            Some(Tile::Wall) | None => {
                 return None;
             }
-            Tile::Box => {
-                group.push(step);
-                step = d.step(&step);
-            }
-            Tile::Open if is_right_box(state, &step) => {
-                group.push(step);
-                step = d.step(&step);
-            }
-            Tile::Open => {
-                return Some(group);
+            Some(Tile::Box(id)) => {
+                if !seen.insert(*id) {
+                    continue;
+                }
+                group.push(*id);
+                let b = &state.boxes[*id];
+                for q in cells(b) {
+                    let next = d.step(&q);
+                    if !b.contains(&next) {
+                        frontier.push(next);
+                    }
+                }
             }
         }
     }
@@ -169,52 +193,28 @@ fn step_horizontally(state: &State, at: &Point2<i32>, d: &Direction) -> Option<V
     Some(group)
 }
 
-fn step_vertically(state: &State, at: &Point2<i32>, d: &Direction) -> Option<Vec<Point2<i32>>> {
-    let mut group = Vec::new();
-
-    let step = d.step(at);
-    match state.grid.get(&step) {
-        Some(Tile::Wall) => {
-            return None;
-        }
-        Some(Tile::Box) => {
-            let side = Direction::East.step(&step);
-            group.push(step);
-            group.push(side);
-            let lhs = step_vertically(state, &step, d);
-            let rhs = step_vertically(state, &side, d);
-            match (lhs, rhs) {
-                (Some(lhs), Some(rhs)) => {
-                    group.extend(lhs);
-                    group.extend(rhs);
-                }
-                _ => {
-                    return None;
-                }
-            }
+fn apply_push(state: &mut State, group: &[usize], d: &Direction) {
+    // Lift every box off the grid before putting any of them back down, so the order the group
+    // was found in never matters.
+    for id in group {
+        for p in cells(&state.boxes[*id]) {
+            state.grid.insert(p, Tile::Open);
         }
-        Some(Tile::Open) if is_right_box(state, &step) => {
-            let side = Direction::West.step(&step);
-            group.push(step);
-            group.push(side);
-            let lhs = step_vertically(state, &step, d);
-            let rhs = step_vertically(state, &side, d);
-            match (lhs, rhs) {
-                (Some(lhs), Some(rhs)) => {
-                    group.extend(lhs);
-                    group.extend(rhs);
-                }
-                _ => {
-                    return None;
-                }
-            }
-        }
-        Some(Tile::Open) => {
-            return Some(group);
+    }
+    for id in group {
+        let b = &state.boxes[*id];
+        let moved = BBox2::new(&d.step(&b.min), &d.step(&b.max));
+        for p in cells(&moved) {
+            state.grid.insert(p, Tile::Box(*id));
         }
-        None => unreachable!(),
+        state.boxes[*id] = moved;
     }
+    state.bot = d.step(&state.bot);
+}
 
+fn push(state: &mut State, d: &Direction) -> Option<Vec<usize>> {
+    let group = find_pushed(state, d)?;
+    apply_push(state, &group, d);
     Some(group)
 }
 
@@ -235,36 +235,354 @@ fn run_bot(state: &mut State) {
             state.movements.remove(0);
         }
 
-        let group = match d {
-            Direction::East | Direction::West => step_horizontally(state, &state.bot, d),
-            Direction::North | Direction::South => step_vertically(state, &state.bot, d),
-        };
+        push(state, d);
+    }
+
+    if DEBUG {
+        println!("Final state:");
+        pprint_grid(state);
+    }
+}
+
+fn direction_to_char(d: &Direction) -> char {
+    match d {
+        Direction::North => '^',
+        Direction::East => '>',
+        Direction::South => 'v',
+        Direction::West => '<',
+    }
+}
+
+fn char_to_direction(ch: char) -> Direction {
+    match ch {
+        '^' => Direction::North,
+        '>' => Direction::East,
+        'v' => Direction::South,
+        '<' => Direction::West,
+        _ => panic!("Unknown movement char: {:?}", ch),
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct JournalEntry {
+    direction: Direction,
+    // None when the move was blocked and nothing (not even the bot) moved.
+    pushed: Option<Vec<usize>>,
+}
+
+#[derive(Debug, PartialEq, Clone)]
+enum JournalError {
+    // A line that isn't a move char followed by box ids or '#'.
+    Unparsed {
+        line: usize,
+        text: String,
+    },
+    // Replaying the move now would push something other than what was recorded, so the journal
+    // belongs to a different warehouse (or a different point in this one).
+    Mismatch {
+        step: usize,
+        recorded: Option<Vec<usize>>,
+        found: Option<Vec<usize>>,
+    },
+}
+
+impl std::fmt::Display for JournalError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Unparsed { line, text } => {
+                write!(f, "Unparsed journal line {}: {:?}.", line, text)
+            }
+            Self::Mismatch {
+                step,
+                recorded,
+                found,
+            } => write!(
+                f,
+                "Journal step {} recorded push {:?}, but the state gives {:?}.",
+                step, recorded, found
+            ),
+        }
+    }
+}
+
+#[derive(Debug, PartialEq, Clone)]
+struct Journal {
+    entries: Vec<JournalEntry>,
+    // Number of entries currently applied to the state. Anything past it can be redone.
+    cursor: usize,
+}
 
-        if let Some(mut group) = group {
-            group.sort_by_key(|p| match d {
-                Direction::North => (p.y, p.x),
-                Direction::South => (-p.y, p.x),
-                Direction::East => (-p.x, p.y),
-                Direction::West => (p.x, p.y),
+impl Journal {
+    fn new() -> Self {
+        Self {
+            entries: Vec::new(),
+            cursor: 0,
+        }
+    }
+
+    fn record(&mut self, state: &mut State, d: &Direction) -> bool {
+        // A fresh move after some undos throws away the old future, like any editor would.
+        self.entries.truncate(self.cursor);
+        let pushed = push(state, d);
+        let moved = pushed.is_some();
+        self.entries.push(JournalEntry {
+            direction: *d,
+            pushed,
+        });
+        self.cursor += 1;
+        moved
+    }
+
+    fn undo(&mut self, state: &mut State) -> bool {
+        if self.cursor == 0 {
+            return false;
+        }
+        self.cursor -= 1;
+        let entry = &self.entries[self.cursor];
+        if let Some(group) = &entry.pushed {
+            // Pulling the same group back the opposite way puts everything where it was, and the
+            // cell the bot retreats into is always the one it just came from.
+            let back = entry.direction.rotate_right().rotate_right();
+            apply_push(state, group, &back);
+        }
+        true
+    }
+
+    fn redo(&mut self, state: &mut State) -> Result<bool, JournalError> {
+        if self.cursor == self.entries.len() {
+            return Ok(false);
+        }
+        // Work the push out again rather than trusting the journal, and leave the state alone if
+        // the two disagree.
+        let entry = &self.entries[self.cursor];
+        let found = find_pushed(state, &entry.direction);
+        if found != entry.pushed {
+            return Err(JournalError::Mismatch {
+                step: self.cursor,
+                recorded: entry.pushed.clone(),
+                found,
             });
-            // TODO: Or fix the recursive aggregation.
-            group.dedup();
-
-            for p in group {
-                let step = d.step(&p);
-                // Exchange is always with an open space. This object is moving into an open space,
-                // and it leaves an equivalent open space behind it.
-                let old = state.grid.insert(p, Tile::Open).unwrap();
-                state.grid.insert(step, old);
+        }
+        if let Some(group) = &found {
+            apply_push(state, group, &entry.direction);
+        }
+        self.cursor += 1;
+        Ok(true)
+    }
+
+    fn seek(&mut self, state: &mut State, step: usize) -> Result<(), JournalError> {
+        let step = step.min(self.entries.len());
+        while self.cursor > step {
+            self.undo(state);
+        }
+        while self.cursor < step {
+            self.redo(state)?;
+        }
+        Ok(())
+    }
+
+    fn to_text(&self) -> String {
+        // One line per step: the move, then the ids of every box it pushed, or '#' if blocked.
+        let mut text = String::new();
+        for entry in &self.entries {
+            text.push(direction_to_char(&entry.direction));
+            match &entry.pushed {
+                Some(group) => {
+                    for id in group {
+                        text.push_str(&format!(" {}", id));
+                    }
+                }
+                None => text.push_str(" #"),
             }
-            state.bot = d.step(&state.bot);
+            text.push('\n');
         }
+        text
     }
 
-    if DEBUG {
-        println!("Final state:");
-        pprint_grid(state);
+    fn from_text(text: &str) -> Result<Self, JournalError> {
+        let mut journal = Self::new();
+        for (i, line) in text.trim().lines().enumerate() {
+            let unparsed = || JournalError::Unparsed {
+                line: i + 1,
+                text: line.to_string(),
+            };
+            let mut parts = line.split_whitespace();
+            let direction = match parts.next() {
+                Some(d) if d.len() == 1 && "^>v<".contains(d) => {
+                    char_to_direction(d.chars().next().unwrap())
+                }
+                _ => return Err(unparsed()),
+            };
+            let rest: Vec<&str> = parts.collect();
+            let pushed = if rest == ["#"] {
+                None
+            } else {
+                let ids: Result<Vec<usize>, _> = rest.iter().map(|id| id.parse()).collect();
+                Some(ids.map_err(|_| unparsed())?)
+            };
+            journal.entries.push(JournalEntry { direction, pushed });
+        }
+        Ok(journal)
+    }
+
+    fn write(&self, path: &std::path::Path) {
+        std::fs::write(path, self.to_text()).expect("Write journal failed!");
     }
+
+    fn read(path: &std::path::Path) -> Result<Self, JournalError> {
+        Self::from_text(&std::fs::read_to_string(path).expect("Read journal failed!"))
+    }
+}
+
+fn run_bot_journaled(state: &mut State) -> Journal {
+    let mut journal = Journal::new();
+    for d in state.movements.clone() {
+        journal.record(state, &d);
+    }
+    journal
+}
+
+fn face(b: &BBox2<i32>, d: &Direction) -> Vec<Point2<i32>> {
+    // The cells just past the box on side d.
+    cells(b)
+        .iter()
+        .map(|p| d.step(p))
+        .filter(|p| !b.contains(p))
+        .collect()
+}
+
+fn is_wall(state: &State, p: &Point2<i32>) -> bool {
+    matches!(state.grid.get(p), Some(Tile::Wall) | None)
+}
+
+fn can_ever_move(state: &State, b: &BBox2<i32>, d: &Direction) -> bool {
+    // Only walls are considered, since they're the only thing that never moves. The box needs
+    // room ahead of it, and somewhere behind it for the bot (or another box) to push from.
+    let back = d.rotate_right().rotate_right();
+    face(b, d).iter().all(|p| !is_wall(state, p))
+        && face(b, &back).iter().any(|p| !is_wall(state, p))
+}
+
+fn is_frozen(state: &State, id: usize, assumed: &mut HashSet<usize>) -> bool {
+    // The usual Sokoban freeze check: a box is frozen when it's stuck on both axes, and a side
+    // is blocked by a wall or by a box that's frozen itself. This box counts as a wall while its
+    // neighbours are checked, so two boxes holding each other in place are both frozen.
+    assumed.insert(id);
+    let b = state.boxes[id].clone();
+    let mut blocked = |p: &Point2<i32>| match state.grid.get(p) {
+        Some(Tile::Open) => false,
+        Some(Tile::Box(j)) => assumed.contains(j) || is_frozen(state, *j, assumed),
+        Some(Tile::Wall) | None => true,
+    };
+    let mut stuck = |d: Direction| {
+        let back = d.rotate_right().rotate_right();
+        face(&b, &d).iter().any(&mut blocked) || face(&b, &back).iter().all(&mut blocked)
+    };
+    let frozen = stuck(Direction::North)
+        && stuck(Direction::South)
+        && stuck(Direction::East)
+        && stuck(Direction::West);
+    assumed.remove(&id);
+    frozen
+}
+
+fn is_wall_locked(state: &State, id: usize, target: &Point2<i32>) -> bool {
+    // A box that can't move along one axis (say, flat against the top wall) can only slide along
+    // the other. If nowhere it could slide to lets it off that line, the target has to be one of
+    // those places.
+    let b = &state.boxes[id];
+    let axes = [
+        (Direction::North, Direction::East),
+        (Direction::East, Direction::North),
+    ];
+    for (across, along) in axes {
+        let mut reach = vec![b.clone()];
+        for d in [along, along.rotate_right().rotate_right()] {
+            let mut at = b.clone();
+            loop {
+                let next = BBox2::new(&d.step(&at.min), &d.step(&at.max));
+                if cells(&next).iter().any(|p| is_wall(state, p)) {
+                    break;
+                }
+                reach.push(next.clone());
+                at = next;
+            }
+        }
+
+        let back = across.rotate_right().rotate_right();
+        let locked = reach
+            .iter()
+            .all(|r| !can_ever_move(state, r, &across) && !can_ever_move(state, r, &back));
+        if locked && reach.iter().all(|r| r.min != *target) {
+            return true;
+        }
+    }
+    false
+}
+
+fn is_deadlocked(state: &State, id: usize, target: &Point2<i32>) -> bool {
+    state.boxes[id].min != *target
+        && (is_frozen(state, id, &mut HashSet::new()) || is_wall_locked(state, id, target))
+}
+
+type PlanKey = (Point2<i32>, Vec<Point2<i32>>);
+
+fn plan(parsed: &State, id: usize, target: &Point2<i32>) -> Option<Vec<Direction>> {
+    // Plain BFS over (bot, every box) so the first solution found is the shortest. The push
+    // engine does all the work, so whatever box shapes were parsed are respected.
+    let key =
+        |state: &State| -> PlanKey { (state.bot, state.boxes.iter().map(|b| b.min).collect()) };
+
+    let mut start = parsed.clone();
+    start.movements.clear();
+    if is_deadlocked(&start, id, target) {
+        return None;
+    }
+
+    let mut parents: HashMap<PlanKey, Option<(PlanKey, Direction)>> = HashMap::new();
+    parents.insert(key(&start), None);
+
+    let mut queue = VecDeque::from([start]);
+    while let Some(state) = queue.pop_front() {
+        if state.boxes[id].min == *target {
+            let mut moves = Vec::new();
+            let mut at = key(&state);
+            while let Some((prev, d)) = parents[&at].clone() {
+                moves.push(d);
+                at = prev;
+            }
+            moves.reverse();
+            return Some(moves);
+        }
+
+        for d in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let mut next = state.clone();
+            if push(&mut next, &d).is_none() || is_deadlocked(&next, id, target) {
+                continue;
+            }
+            let next_key = key(&next);
+            if parents.contains_key(&next_key) {
+                continue;
+            }
+            parents.insert(next_key, Some((key(&state), d)));
+            queue.push_back(next);
+        }
+    }
+
+    None
+}
+
+fn moves_to_string(moves: &[Direction]) -> String {
+    moves.iter().map(direction_to_char).collect()
+}
+
+fn gps(state: &State) -> i32 {
+    state.boxes.iter().map(|b| 100 * b.min.y + b.min.x).sum()
 }
 
 //fn score_box(bbox: &BBox2, at: &Point2) -> i32 {
@@ -288,21 +606,42 @@ fn run_bot(state: &mut State) {
 fn solve(parsed: &State) -> i32 {
     let mut state = parsed.clone();
     run_bot(&mut state);
-
-    let mut accumulator = 0;
-    for (p, t) in state.grid {
-        if t == Tile::Box {
-            accumulator += 100 * p.y + p.x;
-        }
-    }
-
-    accumulator
+    gps(&state)
 }
 
 fn main() {
     let input = load_input(2024, 15);
 
     let parsed = parse_input(input);
+    if EXPORT_JOURNAL || SHOW_STEP.is_some() {
+        let mut state = parsed.clone();
+        let mut journal = run_bot_journaled(&mut state);
+        if EXPORT_JOURNAL {
+            let path = std::path::Path::new("year2024day15b-journal.txt");
+            journal.write(path);
+
+            // Replaying the file from the start has to land on the same warehouse.
+            let mut replayed = Journal::read(path).unwrap_or_else(|e| panic!("{}", e));
+            let mut fresh = parsed.clone();
+            replayed
+                .seek(&mut fresh, usize::MAX)
+                .unwrap_or_else(|e| panic!("{}", e));
+            assert_eq!(state, fresh);
+        }
+        if let Some(step) = SHOW_STEP {
+            journal
+                .seek(&mut state, step)
+                .unwrap_or_else(|e| panic!("{}", e));
+            println!("After step {}:", step);
+            pprint_grid(&state);
+        }
+    }
+    if let Some((id, (x, y))) = PLAN {
+        match plan(&parsed, id, &Point2::new(x, y)) {
+            Some(moves) => println!("Plan for box {}: {}", id, moves_to_string(&moves)),
+            None => println!("Box {} can't be pushed to ({}, {}).", id, x, y),
+        }
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -312,6 +651,7 @@ mod tests {
     use super::*;
 
     #[test]
+    #[allow(clippy::identity_op)]
     fn day15b_example1() {
         let input = "
 ########
@@ -349,9 +689,13 @@ mod tests {
             parsed.grid.get(&Point2::new(1 * 2, 1)).cloned()
         );
         assert_eq!(
-            Some(Tile::Box),
+            Some(Tile::Box(5)),
             parsed.grid.get(&Point2::new(4 * 2, 5)).cloned()
         );
+        assert_eq!(
+            Some(Tile::Box(5)),
+            parsed.grid.get(&Point2::new(4 * 2 + 1, 5)).cloned()
+        );
         assert_eq!(
             Some(Tile::Open),
             parsed.grid.get(&Point2::new(5 * 2, 6)).cloned()
@@ -461,4 +805,286 @@ v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^
         // https://www.reddit.com/r/adventofcode/comments/1heoj7f/comment/m25w22f/
         assert_eq!(618, solve(&parsed));
     }
+
+    #[test]
+    fn day15b_scaled() {
+        let input = "
+##########
+#..O..O.O#
+#......O.#
+#.OO..O.O#
+#..O@..O.#
+#O#..O...#
+#O..O..O.#
+#.OO.O.OO#
+#....O...#
+##########
+
+<vv>^<v^>v>^vv^v>v<>v^v<v<^vv<<<^><<><>>v<vvv<>^v^>^<<<><<v<<<v^vv^v>^
+vvv<<^>^v^^><<>>><>^<<><^vv^^<>vvv<>><^^v>^>vv<>v<<<<v<^v>^<^^>>>^<v<v
+><>vv>v^v^<>><>>>><^^>vv>v<^^^>>v^v^<^^>v^^>v^<^v>v<>>v^v^<v>v^^<^^vv<
+<<v<^>>^^^^>>>v^<>vvv^><v<<<>^^^vv^<vvv>^>v<^^^^v<>^>vvvv><>>v^<<^^^^^
+^><^><>>><>^^<<^^v>>><^<v>^<vv>>v>>>^v><>^v><<<<v>>v<v<v>vvv>^<><<>^><
+^>><>^v<><^vvv<^^<><v<<<<<><^v<<<><<<^^<v<^^^><^>>^<v^><<<^>>^v<v^v<v^
+>^>>^v>vv>^<<^v<>><<><<v<<v><>v<^vv<<<>^^v^>^^>>><<^v>>v^v><^^>>^<>vv^
+<><^^>^^^<><vvvvv^v<v<<>^v<v>v<<^><<><<><<<^^<<<^<<>><<><^^^>^^<>^>v<>
+^^>vv<^v^v<vv>^<><v<^v>^^^>>>^^vvv^>vvv<>>>^<^>>>>>^<<^v>^vvv<>^<><<v>
+v^^>>><<^^<>>^v^<v^vv<>v^<<>^<^v^v><^<<<><<^<v><v<>vv>>v><v^<vv<>v^<<^
+        "
+        .trim()
+        .to_string();
+
+        // Same push rules give the part a answer when boxes are a single cell wide.
+        assert_eq!(10092, solve(&parse_input_scaled(input.clone(), 1)));
+        assert_eq!(9021, solve(&parse_input_scaled(input.clone(), 2)));
+
+        let mut state = parse_input_scaled(input, 3);
+        run_bot(&mut state);
+        for (id, b) in state.boxes.iter().enumerate() {
+            assert_eq!(3, b.max.x - b.min.x + 1);
+            for p in cells(b) {
+                assert_eq!(Some(&Tile::Box(id)), state.grid.get(&p));
+            }
+        }
+    }
+
+    #[test]
+    fn day15b_mixed_boxes() {
+        // A 2x2 box sitting on top of two single-cell boxes.
+        let mut state = parse_input_scaled(
+            "
+#######
+#.....#
+#.....#
+#.....#
+#.....#
+#######
+            "
+            .trim()
+            .to_string(),
+            1,
+        );
+        let tall = state.add_box(BBox2::new(&Point2::new(2, 1), &Point2::new(3, 2)));
+        let left = state.add_box(BBox2::new(&Point2::new(2, 3), &Point2::new(2, 3)));
+        let right = state.add_box(BBox2::new(&Point2::new(3, 3), &Point2::new(3, 3)));
+        state.bot = Point2::new(2, 4);
+
+        // Blocked by the wall above the tall box.
+        assert_eq!(None, push(&mut state, &Direction::North));
+        assert_eq!(Point2::new(2, 4), state.bot);
+
+        // Sideways from the right drags only the narrow box on that row.
+        state.bot = Point2::new(4, 3);
+        assert_eq!(Some(vec![right, left]), push(&mut state, &Direction::West));
+        assert_eq!(Point2::new(1, 3), state.boxes[left].min);
+        assert_eq!(Point2::new(2, 3), state.boxes[right].min);
+
+        // Pushing the tall box right from its left side moves both rows of it at once.
+        state.bot = Point2::new(1, 1);
+        assert_eq!(Some(vec![tall]), push(&mut state, &Direction::East));
+        assert_eq!(
+            BBox2::new(&Point2::new(3, 1), &Point2::new(4, 2)),
+            state.boxes[tall]
+        );
+        assert_eq!(Some(&Tile::Open), state.grid.get(&Point2::new(2, 2)));
+        assert_eq!(100 + 3 + 300 + 1 + 300 + 2, gps(&state));
+    }
+
+    fn journal_example() -> State {
+        // Same warehouse as day15_example4.
+        let input = "
+#######
+#...#.#
+#.....#
+#..OO@#
+#..O..#
+#.....#
+#######
+
+<vv<<^^<<^^
+        "
+        .trim()
+        .to_string();
+        parse_input(input)
+    }
+
+    #[test]
+    fn day15b_journal() {
+        let parsed = journal_example();
+
+        let mut expected = vec![parsed.clone()];
+        let mut state = parsed.clone();
+        for d in &parsed.movements {
+            push(&mut state, d);
+            expected.push(state.clone());
+        }
+
+        let mut state = parsed.clone();
+        let mut journal = run_bot_journaled(&mut state);
+        assert_eq!(parsed.movements.len(), journal.entries.len());
+        assert_eq!(618, gps(&state));
+
+        // Walk all the way back, one step at a time.
+        for i in (0..journal.entries.len()).rev() {
+            assert!(journal.undo(&mut state));
+            assert_eq!(expected[i], state);
+        }
+        assert!(!journal.undo(&mut state));
+
+        journal.seek(&mut state, 4).unwrap();
+        assert_eq!(expected[4], state);
+        assert_eq!(Ok(true), journal.redo(&mut state));
+        assert_eq!(expected[5], state);
+        journal.seek(&mut state, 2).unwrap();
+        assert_eq!(expected[2], state);
+
+        // A new move drops everything that could have been redone.
+        journal.record(&mut state, &Direction::North);
+        assert_eq!(3, journal.entries.len());
+        assert_eq!(Ok(false), journal.redo(&mut state));
+    }
+
+    #[test]
+    fn day15b_journal_replay() {
+        let parsed = journal_example();
+
+        let mut state = parsed.clone();
+        let journal = run_bot_journaled(&mut state);
+        let text = journal.to_text();
+        assert_eq!("< 1 0\nv\nv\n<\n<\n^ 2 1 0\n^ #\n<\n<\n^\n^ 0\n", text);
+
+        let mut replayed = Journal::from_text(&text).unwrap();
+        let mut fresh = parsed.clone();
+        replayed.seek(&mut fresh, usize::MAX).unwrap();
+        assert_eq!(state, fresh);
+        assert_eq!(618, gps(&fresh));
+
+        assert_eq!(
+            Err(JournalError::Unparsed {
+                line: 2,
+                text: "v x".to_string()
+            }),
+            Journal::from_text("< 1 0\nv x\n")
+        );
+        assert!(Journal::from_text("<< 1\n").is_err());
+        assert!(Journal::from_text("< 1\n\n^ #").is_err());
+
+        // With the bot against the left wall the first move is blocked instead of pushing boxes 1
+        // and 0, so the replay stops before it and nothing is touched.
+        let mut replayed = Journal::from_text(&text).unwrap();
+        let mut moved = parsed.clone();
+        moved.bot = Point2::new(2, 1);
+        let before = moved.clone();
+        assert_eq!(
+            Err(JournalError::Mismatch {
+                step: 0,
+                recorded: Some(vec![1, 0]),
+                found: None
+            }),
+            replayed.seek(&mut moved, usize::MAX)
+        );
+        assert_eq!(before, moved);
+        assert_eq!(0, replayed.cursor);
+    }
+
+    #[test]
+    fn day15b_plan() {
+        let input = "
+########
+#......#
+#..O...#
+#.@..O.#
+#......#
+########
+        "
+        .trim()
+        .to_string();
+
+        for scale in [1, 2] {
+            let parsed = parse_input_scaled(input.clone(), scale);
+            let target = Point2::new(6 * scale, 1);
+
+            let moves = plan(&parsed, 0, &target).unwrap();
+
+            let mut state = parsed.clone();
+            state.movements = moves.clone();
+            run_bot(&mut state);
+            assert_eq!(target, state.boxes[0].min);
+
+            // The planned string parses back into the same moves.
+            let replayed =
+                parse_input_scaled(format!("{}\n\n{}", input, moves_to_string(&moves)), scale);
+            assert_eq!(moves, replayed.movements);
+        }
+
+        // Shortest for single-cell boxes: step right to (3, 3) and push the box up once.
+        let parsed = parse_input_scaled(input.clone(), 1);
+        let moves = plan(&parsed, 0, &Point2::new(3, 1)).unwrap();
+        assert_eq!(">^", moves_to_string(&moves));
+    }
+
+    #[test]
+    fn day15b_deadlock() {
+        let input = "
+######
+#O...#
+#..O.#
+#@...#
+######
+        "
+        .trim()
+        .to_string();
+
+        let parsed = parse_input_scaled(input.clone(), 1);
+        assert!(is_deadlocked(&parsed, 0, &Point2::new(2, 2)));
+        assert!(!is_deadlocked(&parsed, 0, &Point2::new(1, 1)));
+        assert!(!is_deadlocked(&parsed, 1, &Point2::new(1, 2)));
+        assert_eq!(None, plan(&parsed, 0, &Point2::new(2, 2)));
+        assert_eq!(Some(vec![]), plan(&parsed, 1, &Point2::new(3, 2)));
+
+        // Pushing the free box into the far corner is a dead end, so the planner has to bring it
+        // along the bottom row instead.
+        let moves = plan(&parsed, 1, &Point2::new(4, 3)).unwrap();
+        let mut state = parsed.clone();
+        state.movements = moves;
+        run_bot(&mut state);
+        assert_eq!(Point2::new(4, 3), state.boxes[1].min);
+    }
+
+    #[test]
+    fn day15b_deadlock_lines() {
+        let input = "
+#######
+#..O..#
+#.....#
+#....@#
+#######
+        "
+        .trim()
+        .to_string();
+
+        // Against the top wall the box can still slide anywhere along it, but never off it.
+        let mut parsed = parse_input_scaled(input.clone(), 1);
+        assert!(!is_deadlocked(&parsed, 0, &Point2::new(1, 1)));
+        assert!(!is_deadlocked(&parsed, 0, &Point2::new(5, 1)));
+        assert!(is_deadlocked(&parsed, 0, &Point2::new(3, 2)));
+        assert_eq!(None, plan(&parsed, 0, &Point2::new(3, 3)));
+        let moves = plan(&parsed, 0, &Point2::new(1, 1)).unwrap();
+        assert_eq!("^^<<<", moves_to_string(&moves));
+
+        // A second box alongside it on the wall and neither can move at all.
+        parsed.add_box(BBox2::new(&Point2::new(4, 1), &Point2::new(4, 1)));
+        assert!(is_deadlocked(&parsed, 0, &Point2::new(1, 1)));
+        assert!(is_deadlocked(&parsed, 1, &Point2::new(5, 1)));
+        assert_eq!(None, plan(&parsed, 0, &Point2::new(1, 1)));
+
+        // Side by side in open floor they're fine.
+        let mut parsed = parse_input_scaled(input, 1);
+        parsed.boxes[0] = BBox2::new(&Point2::new(2, 2), &Point2::new(2, 2));
+        parsed.grid.insert(Point2::new(3, 1), Tile::Open);
+        parsed.grid.insert(Point2::new(2, 2), Tile::Box(0));
+        parsed.add_box(BBox2::new(&Point2::new(3, 2), &Point2::new(3, 2)));
+        assert!(!is_deadlocked(&parsed, 0, &Point2::new(1, 3)));
+        assert!(plan(&parsed, 0, &Point2::new(1, 3)).is_some());
+    }
 }
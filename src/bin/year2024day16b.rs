@@ -4,6 +4,8 @@ use aoc_2024_rs::*;
 use rustc_hash::{FxHashMap, FxHashSet};
 
 const DEBUG: bool = false;
+// Print this many of the cheapest routes (each over different tiles), drawn on the map.
+const SHOW_ROUTES: Option<usize> = None;
 
 #[derive(Debug, PartialEq, Clone)]
 enum Tile {
@@ -112,11 +114,65 @@ impl PartialOrd for Node {
     }
 }
 
+#[derive(Debug, Clone)]
+struct CostModel {
+    step: i32,
+    turn: i32,
+    u_turn: i32,
+    // Extra cost for entering a tile, on top of the step cost.
+    tile_costs: FxHashMap<Point2<i32>, i32>,
+}
+
+impl Default for CostModel {
+    fn default() -> Self {
+        Self {
+            step: 1,
+            turn: 1000,
+            u_turn: 2000,
+            tile_costs: FxHashMap::default(),
+        }
+    }
+}
+
+impl CostModel {
+    fn tile_cost(&self, p: &Point2<i32>) -> i32 {
+        *self.tile_costs.get(p).unwrap_or(&0)
+    }
+}
+
+type Pose = (Point2<i32>, Direction);
+
+fn neighbors(state: &State, costs: &CostModel, (position, direction): Pose) -> Vec<(Pose, i32)> {
+    let forward = direction.step(&position);
+    let candidates = [
+        ((forward, direction), costs.step + costs.tile_cost(&forward)),
+        ((position, direction.rotate_left()), costs.turn),
+        ((position, direction.rotate_right()), costs.turn),
+        (
+            (position, direction.rotate_right().rotate_right()),
+            costs.u_turn,
+        ),
+    ];
+
+    candidates
+        .into_iter()
+        .filter(|((p, d), _)| {
+            if state.grid.get(p) == Some(&Tile::Wall) {
+                return false;
+            }
+            // We're already on this tile if we've rotated on it. Prune paths that would on the
+            // next iteration immediately try to walk into a wall. Mostly because watching this
+            // happen was annoying.
+            !(direction != *d && state.grid.get(&d.step(p)) == Some(&Tile::Wall))
+        })
+        .collect()
+}
+
 // Interestingly, Clippy only complained about "type_complexity" today? Should I feel proud or
 // ashamed that earlier days haven't triggered this already?
 type LowestCostAndDistHashMap = (i32, FxHashMap<(Point2<i32>, Direction), i32>);
 
-fn shortest_paths(state: &State) -> Option<LowestCostAndDistHashMap> {
+fn shortest_paths(state: &State, costs: &CostModel) -> Option<LowestCostAndDistHashMap> {
     let mut dist: FxHashMap<(Point2<i32>, Direction), i32> = FxHashMap::default();
     dist.reserve((state.bbox.max.x * state.bbox.max.y).try_into().unwrap());
 
@@ -146,10 +202,10 @@ fn shortest_paths(state: &State) -> Option<LowestCostAndDistHashMap> {
         if position == state.end_at {
             // Need to hydrate dist costs for all tiles that will appear on a lowest cost path, so
             // keep going until a higher than lowest cost path is found to the end.
-            if lowest_cost.is_none() {
-                lowest_cost = Some(cost);
-            } else if cost > lowest_cost.unwrap() {
-                break;
+            match lowest_cost {
+                None => lowest_cost = Some(cost),
+                Some(lowest) if cost > lowest => break,
+                Some(_) => {}
             }
         }
 
@@ -157,38 +213,14 @@ fn shortest_paths(state: &State) -> Option<LowestCostAndDistHashMap> {
             continue;
         }
 
-        let neighbors = [
-            // walk forward
-            Node {
-                cost: cost + 1,
-                position: direction.step(&position),
-                direction,
-            },
-            // turn left
-            Node {
-                cost: cost + 1000,
-                position,
-                direction: direction.rotate_left(),
-            }, //turn right
-            Node {
-                cost: cost + 1000,
-                position,
-                direction: direction.rotate_right(),
-            },
-        ];
-        for next in neighbors {
-            if state.grid.get(&next.position) == Some(&Tile::Wall) {
-                continue;
-            }
-
-            // We're already on this tile if we've rotated on it. Prune paths that would on the
-            // next iteration immediately try to walk into a wall. Mostly because watching this
-            // happen was annoying.
-            if direction != next.direction
-                && state.grid.get(&next.direction.step(&next.position)) == Some(&Tile::Wall)
-            {
-                continue;
-            }
+        for ((next_position, next_direction), step_cost) in
+            neighbors(state, costs, (position, direction))
+        {
+            let next = Node {
+                cost: cost + step_cost,
+                position: next_position,
+                direction: next_direction,
+            };
 
             // Crucially, instead of requiring strictly lower cost, also now accept less than or
             // equal costs. We want all of the tiles on any lowest cost paths to be hydrated in the
@@ -211,13 +243,211 @@ fn shortest_paths(state: &State) -> Option<LowestCostAndDistHashMap> {
     None
 }
 
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Route {
+    cost: i32,
+    poses: Vec<Pose>,
+}
+
+impl Route {
+    fn tiles(&self) -> Vec<Point2<i32>> {
+        let mut tiles: Vec<Point2<i32>> = self.poses.iter().map(|(p, _)| *p).collect();
+        tiles.dedup();
+        tiles
+    }
+}
+
+fn route_cost(state: &State, costs: &CostModel, poses: &[Pose]) -> i32 {
+    poses
+        .windows(2)
+        .map(|w| {
+            neighbors(state, costs, w[0])
+                .into_iter()
+                .find(|(pose, _)| *pose == w[1])
+                .map(|(_, c)| c)
+                .expect("Route has a gap!")
+        })
+        .sum()
+}
+
+fn cheapest_route(
+    state: &State,
+    costs: &CostModel,
+    from: Pose,
+    banned_poses: &FxHashSet<Pose>,
+    banned_moves: &FxHashSet<(Pose, Pose)>,
+) -> Option<Route> {
+    // Same Dijkstra as above, except it only needs one path, remembers how it got anywhere, and
+    // can be told to avoid some poses and moves (which is all Yen's algorithm needs).
+    let mut dist: FxHashMap<Pose, i32> = FxHashMap::default();
+    let mut parents: FxHashMap<Pose, Pose> = FxHashMap::default();
+    let mut heap = BinaryHeap::new();
+
+    dist.insert(from, 0);
+    heap.push(Node {
+        cost: 0,
+        position: from.0,
+        direction: from.1,
+    });
+
+    while let Some(Node {
+        cost,
+        position,
+        direction,
+    }) = heap.pop()
+    {
+        let pose = (position, direction);
+        if position == state.end_at {
+            let mut poses = vec![pose];
+            while let Some(prev) = parents.get(poses.last().unwrap()) {
+                poses.push(*prev);
+            }
+            poses.reverse();
+            return Some(Route { cost, poses });
+        }
+
+        if cost > *dist.get(&pose).unwrap_or(&i32::MAX) {
+            continue;
+        }
+
+        for (next, step_cost) in neighbors(state, costs, pose) {
+            if banned_poses.contains(&next) || banned_moves.contains(&(pose, next)) {
+                continue;
+            }
+            let next_cost = cost + step_cost;
+            if next_cost < *dist.get(&next).unwrap_or(&i32::MAX) {
+                dist.insert(next, next_cost);
+                parents.insert(next, pose);
+                heap.push(Node {
+                    cost: next_cost,
+                    position: next.0,
+                    direction: next.1,
+                });
+            }
+        }
+    }
+
+    None
+}
+
+fn k_cheapest_routes(state: &State, costs: &CostModel, k: usize) -> Vec<Route> {
+    // Yen's algorithm: every next best route leaves one of the routes found so far at some
+    // point (the spur), so try leaving each of them at every point in every way not yet taken.
+    //
+    // Yen's works on poses, but turning around on a tile can be done left-left, right-right or
+    // as a U-turn for the same cost. Those all still get spurred from, but only the first route
+    // over each sequence of tiles is returned.
+    let start = (state.start_at, state.start_face);
+    let mut found: Vec<Route> = Vec::new();
+    let mut candidates: Vec<Route> = Vec::new();
+    let mut routes: Vec<Route> = Vec::new();
+
+    match cheapest_route(
+        state,
+        costs,
+        start,
+        &FxHashSet::default(),
+        &FxHashSet::default(),
+    ) {
+        Some(route) => {
+            found.push(route.clone());
+            routes.push(route);
+        }
+        None => return routes,
+    }
+
+    while routes.len() < k {
+        let last = found.last().unwrap().poses.clone();
+        for i in 0..last.len() - 1 {
+            let spur = last[i];
+            let root = &last[..=i];
+
+            let banned_moves: FxHashSet<(Pose, Pose)> = found
+                .iter()
+                .filter(|r| r.poses.len() > i + 1 && r.poses[..=i] == *root)
+                .map(|r| (r.poses[i], r.poses[i + 1]))
+                .collect();
+            let banned_poses: FxHashSet<Pose> = root[..i].iter().cloned().collect();
+
+            if let Some(spur_route) =
+                cheapest_route(state, costs, spur, &banned_poses, &banned_moves)
+            {
+                let mut poses = root[..i].to_vec();
+                poses.extend(spur_route.poses);
+                let candidate = Route {
+                    cost: route_cost(state, costs, &poses),
+                    poses,
+                };
+                if !found.contains(&candidate) && !candidates.contains(&candidate) {
+                    candidates.push(candidate);
+                }
+            }
+        }
+
+        if candidates.is_empty() {
+            break;
+        }
+        // Ties go to the shorter route, then whatever was found first.
+        let best = (0..candidates.len())
+            .min_by_key(|i| (candidates[*i].cost, candidates[*i].poses.len(), *i))
+            .unwrap();
+        let route = candidates.remove(best);
+        if routes.iter().all(|r| r.tiles() != route.tiles()) {
+            routes.push(route.clone());
+        }
+        found.push(route);
+    }
+
+    routes
+}
+
+fn render_route(state: &State, route: &Route) -> String {
+    // Like the puzzle's own pictures: the last facing on each tile, with S and E left alone.
+    let mut facing: FxHashMap<Point2<i32>, Direction> = FxHashMap::default();
+    for (p, d) in &route.poses {
+        facing.insert(*p, *d);
+    }
+
+    let mut out = String::new();
+    for y in state.bbox.min.y..=state.bbox.max.y {
+        for x in state.bbox.min.x..=state.bbox.max.x {
+            let p = Point2::new(x, y);
+            let c = if p == state.start_at {
+                'S'
+            } else if p == state.end_at {
+                'E'
+            } else if let Some(d) = facing.get(&p) {
+                match d {
+                    Direction::North => '^',
+                    Direction::East => '>',
+                    Direction::South => 'v',
+                    Direction::West => '<',
+                }
+            } else {
+                match state.grid.get(&p) {
+                    Some(Tile::Wall) => '#',
+                    Some(Tile::Open) => '.',
+                    None => unreachable!("Grid is malformed!"),
+                }
+            };
+            out.push(c);
+        }
+        out.push('\n');
+    }
+    out
+}
+
 fn solve(parsed: &State) -> i32 {
+    solve_with(parsed, &CostModel::default())
+}
+
+fn solve_with(parsed: &State, costs: &CostModel) -> i32 {
     let mut state = parsed.clone();
 
     let start_at = state.start_at;
     let end_at = state.end_at;
 
-    let (value, forward_dist) = shortest_paths(&state).unwrap();
+    let (value, forward_dist) = shortest_paths(&state, costs).unwrap();
 
     // Now it's time to try out this:
     // https://math.stackexchange.com/questions/998848/can-i-use-dijkstras-algorith-for-finding-all-shortest-paths
@@ -242,13 +472,16 @@ fn solve(parsed: &State) -> i32 {
         state.start_at = end_at;
         state.start_face = end_d.rotate_right().rotate_right();
         state.end_at = start_at;
-        let (_, backward_dist) = shortest_paths(&state).unwrap();
+        let (_, backward_dist) = shortest_paths(&state, costs).unwrap();
         backward_dists.push(backward_dist);
     }
 
     // Probably don't have to try all points and all directions, but I kind of want to be done.
     // This should be a bunch of quick HashMap checks and some simple math, so it should be fast,
     // but... Well, it's faster with FxHash anyway.
+    //
+    // Tile costs are paid on entry, so the backward search paid for p but not for the end tile,
+    // the opposite of what the forward path from p would pay.
 
     let mut best_path_points = FxHashSet::default();
     for y in state.bbox.min.y..=state.bbox.max.y {
@@ -266,7 +499,7 @@ fn solve(parsed: &State) -> i32 {
                     }
                     let f = forward_dist.get(&forward_candidate).unwrap();
                     let b = backward_dist.get(&backward_candidate).unwrap();
-                    if f + b == value {
+                    if f + b - costs.tile_cost(&p) + costs.tile_cost(&end_at) == value {
                         best_path_points.insert(p);
                     }
                 }
@@ -280,6 +513,13 @@ fn solve(parsed: &State) -> i32 {
 fn main() {
     let input = load_input(2024, 16);
     let parsed = parse_input(input);
+    if let Some(k) = SHOW_ROUTES {
+        let routes = k_cheapest_routes(&parsed, &CostModel::default(), k);
+        for (i, route) in routes.iter().enumerate() {
+            println!("#{} costs {}:", i + 1, route.cost);
+            println!("{}", render_route(&parsed, route));
+        }
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -355,4 +595,128 @@ mod tests {
 
         assert_eq!(64, solve(&parsed));
     }
+
+    #[test]
+    fn day16b_cost_model() {
+        let input = "
+#####
+#..E#
+#.#.#
+#S..#
+#####
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        let mut costs = CostModel::default();
+        assert_eq!(1004, shortest_paths(&parsed, &costs).unwrap().0);
+        assert_eq!(5, solve_with(&parsed, &costs));
+
+        costs.turn = 1;
+        assert_eq!(5, shortest_paths(&parsed, &costs).unwrap().0);
+        costs.tile_costs.insert(Point2::new(2, 3), 10);
+        assert_eq!(6, shortest_paths(&parsed, &costs).unwrap().0);
+        assert_eq!(5, solve_with(&parsed, &costs));
+
+        // Both ways round cost the same now, so every open tile is on a best path.
+        costs.tile_costs.insert(Point2::new(2, 3), 1);
+        assert_eq!(6, shortest_paths(&parsed, &costs).unwrap().0);
+        assert_eq!(8, solve_with(&parsed, &costs));
+    }
+
+    #[test]
+    fn day16b_k_cheapest_routes() {
+        let input = "
+#####
+#..E#
+#.#.#
+#S..#
+#####
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        let routes = k_cheapest_routes(&parsed, &CostModel::default(), 2);
+        assert_eq!(
+            vec![1004, 2004],
+            routes.iter().map(|r| r.cost).collect::<Vec<_>>()
+        );
+        assert_eq!(
+            "#####\n#..E#\n#.#^#\n#S>^#\n#####\n",
+            render_route(&parsed, &routes[0])
+        );
+        assert_eq!(
+            "#####\n#>>E#\n#^#.#\n#S..#\n#####\n",
+            render_route(&parsed, &routes[1])
+        );
+
+        let input = "
+###############
+#.......#....E#
+#.#.###.#.###.#
+#.....#.#...#.#
+#.###.#####.#.#
+#.#.#.......#.#
+#.#.#####.###.#
+#...........#.#
+###.#.#####.#.#
+#...#.....#.#.#
+#.#.#.###.#.#.#
+#.....#...#.#.#
+#.###.#.#.#.#.#
+#S..#.....#...#
+###############
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+        let costs = CostModel::default();
+
+        // The example has three different best paths.
+        let routes = k_cheapest_routes(&parsed, &costs, 5);
+        assert_eq!(5, routes.len());
+        assert_eq!(
+            vec![7036, 7036, 7036],
+            routes[..3].iter().map(|r| r.cost).collect::<Vec<_>>()
+        );
+        for (i, route) in routes.iter().enumerate() {
+            assert_eq!(route.cost, route_cost(&parsed, &costs, &route.poses));
+            assert_eq!(parsed.end_at, route.poses.last().unwrap().0);
+            assert!(route.cost > 7036 || i < 3);
+            assert!(!routes[..i].contains(route));
+            assert!(routes[..i].iter().all(|r| r.tiles() != route.tiles()));
+        }
+    }
+
+    #[test]
+    fn day16b_k_routes_distinct_tiles() {
+        // Facing the wall, the reindeer has to turn around first. Left-left, right-right and the
+        // U-turn all cost 2000, and without deduping those came back as three separate routes
+        // over the same tiles.
+        let input = "
+#####
+#...#
+#E.S#
+#...#
+#####
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+        let costs = CostModel::default();
+
+        let routes = k_cheapest_routes(&parsed, &costs, 4);
+        assert_eq!(4, routes.len());
+        assert_eq!(
+            vec![Point2::new(3, 2), Point2::new(2, 2), Point2::new(1, 2)],
+            routes[0].tiles()
+        );
+        assert_eq!(2002, routes[0].cost);
+        for (i, route) in routes.iter().enumerate() {
+            assert!(i == 0 || routes[i - 1].cost <= route.cost);
+            assert!(routes[..i].iter().all(|r| r.tiles() != route.tiles()));
+        }
+    }
 }
@@ -2,6 +2,9 @@ use std::collections::{BinaryHeap, HashMap, HashSet};
 
 use aoc_2024_rs::*;
 
+// Also race the bytes as they fall, rather than waiting for the first ones to land.
+const RACE: bool = false;
+
 #[derive(Debug, Clone)]
 struct State {
     obstacles: Vec<Point2<i32>>,
@@ -134,6 +137,62 @@ fn tick(state: &mut State, count: usize) {
     }
 }
 
+fn race(state: &State) -> Option<(usize, Vec<Point2<i32>>)> {
+    // Byte i lands at time i, and the walker can never be standing where a byte has landed. The
+    // walker may stay put, but bytes only ever pile up, so waiting somewhere never opens a way
+    // that stepping on straight away didn't. That makes it a plain BFS where the time is the
+    // distance, with the byte check done at the time each cell would be entered, and parents
+    // only needs the first arrival at each cell.
+    let mut fallen_at: HashMap<Point2<i32>, usize> = HashMap::new();
+    for (t, p) in state.obstacles.iter().enumerate() {
+        fallen_at.entry(*p).or_insert(t);
+    }
+    let is_corrupted = |p: &Point2<i32>, t: usize| fallen_at.get(p).is_some_and(|f| *f <= t);
+
+    if is_corrupted(&state.start_at, 0) {
+        return None;
+    }
+
+    let mut parents: HashMap<Point2<i32>, Point2<i32>> = HashMap::new();
+    parents.insert(state.start_at, state.start_at);
+    let mut frontier = vec![state.start_at];
+
+    for t in 0.. {
+        if parents.contains_key(&state.end_at) {
+            let mut route = vec![state.end_at];
+            while *route.last().unwrap() != state.start_at {
+                route.push(parents[route.last().unwrap()]);
+            }
+            route.reverse();
+            return Some((t, route));
+        }
+
+        let mut next = Vec::new();
+        for p in &frontier {
+            for d in [
+                Direction::North,
+                Direction::East,
+                Direction::South,
+                Direction::West,
+            ] {
+                let q = d.step(p);
+                if state.bbox.contains(&q) && !is_corrupted(&q, t + 1) && !parents.contains_key(&q)
+                {
+                    parents.insert(q, *p);
+                    next.push(q);
+                }
+            }
+        }
+
+        if next.is_empty() {
+            return None;
+        }
+        frontier = next;
+    }
+
+    unreachable!()
+}
+
 fn solve(parsed: &State, depth: usize) -> usize {
     let mut state = parsed.clone();
     tick(&mut state, depth);
@@ -143,6 +202,12 @@ fn solve(parsed: &State, depth: usize) -> usize {
 fn main() {
     let input = load_input(2024, 18);
     let parsed = parse_input(input);
+    if RACE {
+        match race(&parsed) {
+            Some((t, route)) => println!("Racing the bytes: {} steps over {:?}", t, route),
+            None => println!("Racing the bytes: can't get out!"),
+        }
+    }
     let answer = solve(&parsed, 1024);
     println!("Answer: {:?}", answer);
 }
@@ -214,7 +279,71 @@ mod tests {
             pprint_grid(&state)
         );
 
+        let state = parsed.clone();
+        assert_eq!(22, solve(&state, 12));
+    }
+
+    #[test]
+    fn day18a_race() {
+        let input = "
+5,4
+4,2
+4,5
+3,0
+2,1
+6,3
+2,4
+1,5
+0,6
+3,3
+2,6
+5,1
+1,2
+5,5
+2,5
+6,5
+1,4
+0,4
+6,4
+1,1
+6,1
+1,0
+0,5
+1,6
+2,0
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        let (arrival, route) = race(&parsed).unwrap();
+        assert_eq!(arrival + 1, route.len());
+        assert_eq!(parsed.start_at, route[0]);
+        assert_eq!(parsed.end_at, route[arrival]);
+        for (t, p) in route.iter().enumerate() {
+            assert!(!parsed.obstacles[..=t.min(parsed.obstacles.len() - 1)].contains(p));
+            if t > 0 {
+                assert!(p.manhattan_distance(&route[t - 1]) <= 1);
+            }
+        }
+        // The bytes never get in the way of a shortest walk across the example.
+        assert_eq!(12, arrival);
+        assert_eq!(13, route.len());
+
+        // Blocked for good before the walker can get anywhere near.
+        let parsed = parse_input("1,0 0,1".to_string());
         let mut state = parsed.clone();
-        assert_eq!(22, solve(&mut state, 12));
+        state.bbox = BBox2::new(&Point2::new(0, 0), &Point2::new(3, 3));
+        state.end_at = state.bbox.max;
+        assert_eq!(None, race(&state));
+
+        // A byte landing on the end after the walker has left it alone is fine, but landing on
+        // it first isn't.
+        let mut state = parse_input("0,0 3,3".to_string());
+        state.obstacles = vec![Point2::new(2, 2), Point2::new(3, 3)];
+        assert_eq!(None, race(&state));
+        state.obstacles = vec![Point2::new(2, 2); 10];
+        state.obstacles.push(Point2::new(3, 3));
+        assert_eq!(6, race(&state).unwrap().0);
     }
 }
@@ -1,5 +1,8 @@
 use aoc_2024_rs::*;
-use rustc_hash::FxHashSet;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+// Re-run a plain search after every byte and check the collapse agrees with it (slow).
+const CHECK_COLLAPSE: bool = false;
 
 #[derive(Debug, Clone)]
 struct State {
@@ -116,33 +119,207 @@ fn tick(state: &mut State, count: usize) {
     }
 }
 
-fn solve(parsed: &State, depth: usize) -> String {
-    // Binary search for this first condition change.
+struct DisjointSet {
+    parents: Vec<usize>,
+    // Kept on every root so whole components can be walked when they merge.
+    members: Vec<Vec<usize>>,
+}
 
-    let mut left = depth;
-    let mut right = parsed.obstacles.len();
+impl DisjointSet {
+    fn new(size: usize) -> Self {
+        Self {
+            parents: (0..size).collect(),
+            members: (0..size).map(|i| vec![i]).collect(),
+        }
+    }
+
+    fn find(&mut self, mut i: usize) -> usize {
+        while self.parents[i] != i {
+            self.parents[i] = self.parents[self.parents[i]];
+            i = self.parents[i];
+        }
+        i
+    }
 
-    let mut state = parsed.clone();
-    while left <= right {
-        let m = (left + right) / 2;
-        state = parsed.clone();
-        tick(&mut state, m);
-        if has_path(&state) {
-            left = m + 1;
-        } else {
-            right = m - 1;
+    fn union(&mut self, a: usize, b: usize) -> usize {
+        let mut a = self.find(a);
+        let mut b = self.find(b);
+        if a == b {
+            return a;
+        }
+        if self.members[a].len() < self.members[b].len() {
+            std::mem::swap(&mut a, &mut b);
         }
+        self.parents[b] = a;
+        let moved = std::mem::take(&mut self.members[b]);
+        self.members[a].extend(moved);
+        a
     }
+}
 
-    let p = state.obstacles[left - 1];
-    format!("{},{}", p.x, p.y)
+#[derive(Debug, PartialEq, Clone)]
+struct Collapse {
+    // Whether start and end are connected once the first t bytes have fallen, for every t.
+    connected: Vec<bool>,
+    // The first t where they aren't, if that ever happens.
+    blocked_at: Option<usize>,
+    // For each cell, the first t where it can't be reached from the start (because it was cut
+    // off, or because it was hit). None if it can always be reached.
+    unreachable_at: FxHashMap<Point2<i32>, Option<usize>>,
+}
+
+fn collapse(state: &State) -> Collapse {
+    // Going forwards, bytes only ever cut the grid apart, which union-find can't do. Going
+    // backwards from the fully corrupted grid, removing bytes only ever joins it back together,
+    // which is exactly what union-find is good at.
+    let w = (state.bbox.max.x - state.bbox.min.x + 1) as usize;
+    let h = (state.bbox.max.y - state.bbox.min.y + 1) as usize;
+    let index = |p: &Point2<i32>| -> usize {
+        (p.y - state.bbox.min.y) as usize * w + (p.x - state.bbox.min.x) as usize
+    };
+    let point = |i: usize| -> Point2<i32> {
+        Point2::new(
+            state.bbox.min.x + (i % w) as i32,
+            state.bbox.min.y + (i / w) as i32,
+        )
+    };
+
+    // The same byte can fall on a cell more than once, only the first one matters.
+    let mut fallen_at: Vec<Option<usize>> = vec![None; w * h];
+    for (t, p) in state.obstacles.iter().enumerate() {
+        if fallen_at[index(p)].is_none() {
+            fallen_at[index(p)] = Some(t);
+        }
+    }
+
+    let n = state.obstacles.len();
+    let start = index(&state.start_at);
+    let end = index(&state.end_at);
+
+    let mut sets = DisjointSet::new(w * h);
+    let mut open = vec![false; w * h];
+    let mut reachable_until: Vec<Option<Option<usize>>> = vec![None; w * h];
+    let mut connected = vec![false; n + 1];
+
+    // Opens cell i in the grid of the first t bytes. Anything that ends up joined to the start
+    // for the first time could be reached up to t, so was cut off at t + 1. Only the components
+    // being merged in are walked, everything already joined to the start was marked back then.
+    let mut open_cell = |sets: &mut DisjointSet, open: &mut [bool], i: usize, t: usize| {
+        let start_root = open[start].then(|| sets.find(start));
+        open[i] = true;
+        let p = point(i);
+        let mut roots = vec![sets.find(i)];
+        for d in [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ] {
+            let q = d.step(&p);
+            if state.bbox.contains(&q) && open[index(&q)] {
+                let root = sets.find(index(&q));
+                if !roots.contains(&root) {
+                    roots.push(root);
+                }
+            }
+        }
+
+        if i == start || roots.iter().any(|r| Some(*r) == start_root) {
+            for root in roots.iter().filter(|r| Some(**r) != start_root) {
+                for member in &sets.members[*root] {
+                    if reachable_until[*member].is_none() {
+                        reachable_until[*member] = Some(if t == n { None } else { Some(t + 1) });
+                    }
+                }
+            }
+        }
+        for root in &roots[1..] {
+            sets.union(roots[0], *root);
+        }
+    };
+    let is_connected = |sets: &mut DisjointSet, open: &[bool]| -> bool {
+        open[start] && open[end] && sets.find(start) == sets.find(end)
+    };
+
+    for (i, fallen) in fallen_at.iter().enumerate() {
+        if fallen.is_none() {
+            open_cell(&mut sets, &mut open, i, n);
+        }
+    }
+    connected[n] = is_connected(&mut sets, &open);
+
+    for t in (0..n).rev() {
+        let i = index(&state.obstacles[t]);
+        if fallen_at[i] == Some(t) {
+            open_cell(&mut sets, &mut open, i, t);
+        }
+        connected[t] = is_connected(&mut sets, &open);
+    }
+
+    let blocked_at = connected.iter().position(|c| !c);
+    let unreachable_at = reachable_until
+        .iter()
+        .enumerate()
+        .map(|(i, r)| (point(i), r.unwrap_or(Some(0))))
+        .collect();
+
+    Collapse {
+        connected,
+        blocked_at,
+        unreachable_at,
+    }
+}
+
+#[allow(dead_code)]
+fn pprint_collapse(state: &State, collapse: &Collapse, t: usize) -> String {
+    // The grid after t bytes, with 'O' on everything still reachable from the start.
+    let fallen: FxHashSet<Point2<i32>> = state.obstacles.iter().take(t).cloned().collect();
+    let mut s = String::new();
+    for y in state.bbox.min.y..=state.bbox.max.y {
+        for x in state.bbox.min.x..=state.bbox.max.x {
+            let p = Point2::new(x, y);
+            let c = if fallen.contains(&p) {
+                '#'
+            } else {
+                match collapse.unreachable_at[&p] {
+                    Some(u) if u <= t => '.',
+                    _ => 'O',
+                }
+            };
+            s.push(c);
+        }
+        s.push('\n');
+    }
+    s.trim().to_string()
+}
+
+fn solve(parsed: &State, depth: usize) -> Option<String> {
+    // The puzzle promises the first depth bytes leave a path, so only look after that. There's
+    // no byte to blame if the path is never blocked, or is blocked before any have fallen.
+    let collapse = collapse(parsed);
+    let t = depth + collapse.connected.get(depth..)?.iter().position(|c| !c)?;
+    let p = parsed.obstacles.get(t.checked_sub(1)?)?;
+    Some(format!("{},{}", p.x, p.y))
 }
 
 fn main() {
     let input = load_input(2024, 18);
 
     let parsed = parse_input(input);
-    let answer = solve(&parsed, 1024);
+    if CHECK_COLLAPSE {
+        let collapse = collapse(&parsed);
+        let mut state = parsed.clone();
+        for t in 0..=parsed.obstacles.len() {
+            tick(&mut state, t);
+            assert_eq!(
+                has_path(&state),
+                collapse.connected[t],
+                "Collapse disagrees after {} bytes!",
+                t
+            );
+        }
+    }
+    let answer = solve(&parsed, 1024).expect("Path never blocked!");
     println!("Answer: {:?}", answer);
 }
 
@@ -213,7 +390,81 @@ mod tests {
             pprint_grid(&state)
         );
 
-        let mut state = parsed.clone();
-        assert_eq!("6,1", solve(&mut state, 12));
+        let state = parsed.clone();
+        assert_eq!(Some("6,1".to_string()), solve(&state, 12));
+    }
+
+    #[test]
+    fn day18b_collapse() {
+        let input = "
+5,4
+4,2
+4,5
+3,0
+2,1
+6,3
+2,4
+1,5
+0,6
+3,3
+2,6
+5,1
+1,2
+5,5
+2,5
+6,5
+1,4
+0,4
+6,4
+1,1
+6,1
+1,0
+0,5
+1,6
+2,0
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+        let collapse = collapse(&parsed);
+
+        assert_eq!(parsed.obstacles.len() + 1, collapse.connected.len());
+        assert_eq!(Some(21), collapse.blocked_at);
+        assert_eq!(Point2::new(6, 1), parsed.obstacles[20]);
+
+        // Only the first 20 bytes never block the path.
+        let mut first = parsed.clone();
+        first.obstacles.truncate(20);
+        assert_eq!(None, solve(&first, 12));
+        assert_eq!(None, solve(&parsed, parsed.obstacles.len() + 2));
+        for t in 0..=parsed.obstacles.len() {
+            let mut state = parsed.clone();
+            tick(&mut state, t);
+            assert_eq!(has_path(&state), collapse.connected[t]);
+        }
+
+        // The start is never cut off, the first byte's cell goes when it's hit, and the end goes
+        // with the rest of its side of the grid at the blocking byte.
+        assert_eq!(None, collapse.unreachable_at[&parsed.start_at]);
+        assert_eq!(Some(1), collapse.unreachable_at[&Point2::new(5, 4)]);
+        assert_eq!(Some(21), collapse.unreachable_at[&parsed.end_at]);
+
+        // Only the corner pocket at (1, 6) has been sealed off after 12 bytes.
+        assert_eq!(Some(11), collapse.unreachable_at[&Point2::new(1, 6)]);
+
+        assert_eq!(
+            "
+OOO#OOO
+OO#OO#O
+OOOO#OO
+OOO#OO#
+OO#OO#O
+O#OO#OO
+#.#OOOO
+        "
+            .trim()
+            .to_string(),
+            pprint_collapse(&parsed, &collapse, 12)
+        );
     }
 }
@@ -1,8 +1,6 @@
 use aoc_2024_rs::*;
-use cached::proc_macro::cached;
-use cached::UnboundCache;
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 enum Color {
     // MTG Color Wheel?!
     W, // white
@@ -62,31 +60,36 @@ fn parse_input(input: String) -> State {
     state
 }
 
-#[cached(
-    ty = "UnboundCache<String, bool>",
-    create = "{ UnboundCache::new() }",
-    convert = r#"{ format!("{:?}", design) }"#
-)]
 fn is_possible(patterns: &Vec<Vec<Color>>, design: &[Color]) -> bool {
-    if design.is_empty() {
-        return false;
-    }
-    for pattern in patterns {
-        if pattern.len() == design.len() && pattern == design {
-            return true;
-        }
-        if pattern.len() > design.len() {
-            continue;
-        }
-        if !design.starts_with(pattern) {
-            continue;
-        }
-        if is_possible(patterns, &design[pattern.len()..]) {
-            return true;
+    fn inner(
+        patterns: &Vec<Vec<Color>>,
+        design: &[Color],
+        memo: &mut Memo<Vec<Color>, bool>,
+    ) -> bool {
+        if design.is_empty() {
+            return false;
         }
+        memo.get_or_compute(design.to_vec(), |memo| {
+            for pattern in patterns {
+                if pattern.len() == design.len() && pattern == design {
+                    return true;
+                }
+                if pattern.len() > design.len() {
+                    continue;
+                }
+                if !design.starts_with(pattern) {
+                    continue;
+                }
+                if inner(patterns, &design[pattern.len()..], memo) {
+                    return true;
+                }
+            }
+
+            false
+        })
     }
 
-    false
+    inner(patterns, design, &mut Memo::new())
 }
 
 fn solve(parsed: &State) -> usize {
@@ -154,14 +157,14 @@ bbrgwb
             parsed
         );
 
-        assert_eq!(true, is_possible(&parsed.patterns, &parsed.designs[0]));
-        assert_eq!(true, is_possible(&parsed.patterns, &parsed.designs[1]));
-        assert_eq!(true, is_possible(&parsed.patterns, &parsed.designs[2]));
-        assert_eq!(true, is_possible(&parsed.patterns, &parsed.designs[3]));
-        assert_eq!(false, is_possible(&parsed.patterns, &parsed.designs[4]));
-        assert_eq!(true, is_possible(&parsed.patterns, &parsed.designs[5]));
-        assert_eq!(true, is_possible(&parsed.patterns, &parsed.designs[6]));
-        assert_eq!(false, is_possible(&parsed.patterns, &parsed.designs[7]));
+        assert!(is_possible(&parsed.patterns, &parsed.designs[0]));
+        assert!(is_possible(&parsed.patterns, &parsed.designs[1]));
+        assert!(is_possible(&parsed.patterns, &parsed.designs[2]));
+        assert!(is_possible(&parsed.patterns, &parsed.designs[3]));
+        assert!(!is_possible(&parsed.patterns, &parsed.designs[4]));
+        assert!(is_possible(&parsed.patterns, &parsed.designs[5]));
+        assert!(is_possible(&parsed.patterns, &parsed.designs[6]));
+        assert!(!is_possible(&parsed.patterns, &parsed.designs[7]));
 
         assert_eq!(6, solve(&parsed));
     }
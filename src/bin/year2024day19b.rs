@@ -1,8 +1,11 @@
 use aoc_2024_rs::*;
-use cached::proc_macro::cached;
-use cached::UnboundCache;
 
-#[derive(Debug, PartialEq, Eq)]
+// Print why each impossible design can't be made, and what pattern would fix it.
+const DIAGNOSE: bool = false;
+// Print the fewest towels and up to this many arrangements for each possible design.
+const SHOW_ARRANGEMENTS: Option<usize> = None;
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 enum Color {
     // MTG Color Wheel?!
     W, // white
@@ -25,6 +28,31 @@ impl From<char> for Color {
     }
 }
 
+impl Color {
+    fn index(&self) -> usize {
+        match self {
+            Color::W => 0,
+            Color::U => 1,
+            Color::B => 2,
+            Color::R => 3,
+            Color::G => 4,
+        }
+    }
+}
+
+fn colors_to_string(colors: &[Color]) -> String {
+    colors
+        .iter()
+        .map(|c| match c {
+            Color::W => 'w',
+            Color::U => 'u',
+            Color::B => 'b',
+            Color::R => 'r',
+            Color::G => 'g',
+        })
+        .collect()
+}
+
 #[derive(Debug, PartialEq, Eq)]
 struct State {
     patterns: Vec<Vec<Color>>,
@@ -62,44 +90,316 @@ fn parse_input(input: String) -> State {
     state
 }
 
-#[cached(
-    ty = "UnboundCache<String, u64>",
-    create = "{ UnboundCache::new() }",
-    convert = r#"{ format!("{:?}", design) }"#
-)]
-fn is_possible(patterns: &Vec<Vec<Color>>, design: &[Color]) -> u64 {
-    let mut acc = 0;
-    if design.is_empty() {
-        return acc;
+#[derive(Debug, Default)]
+struct TrieNode {
+    children: [Option<usize>; 5],
+    // Index of the pattern that ends here, if any. Duplicate patterns keep the first.
+    pattern: Option<usize>,
+}
+
+#[derive(Debug)]
+struct Trie {
+    nodes: Vec<TrieNode>,
+}
+
+impl Trie {
+    fn new(patterns: &[Vec<Color>]) -> Self {
+        let mut nodes = vec![TrieNode::default()];
+        for (i, pattern) in patterns.iter().enumerate() {
+            let mut at = 0;
+            for color in pattern {
+                at = match nodes[at].children[color.index()] {
+                    Some(next) => next,
+                    None => {
+                        nodes.push(TrieNode::default());
+                        let next = nodes.len() - 1;
+                        nodes[at].children[color.index()] = Some(next);
+                        next
+                    }
+                };
+            }
+            if at != 0 && nodes[at].pattern.is_none() {
+                nodes[at].pattern = Some(i);
+            }
+        }
+        Self { nodes }
+    }
+
+    fn matches_at(&self, design: &[Color], start: usize) -> Vec<(usize, usize)> {
+        // Every (pattern, length) that fits the design starting from start, shortest first. One
+        // walk down the trie instead of comparing against every pattern.
+        let mut matches = Vec::new();
+        let mut at = 0;
+        for (i, color) in design[start..].iter().enumerate() {
+            match self.nodes[at].children[color.index()] {
+                Some(next) => at = next,
+                None => break,
+            }
+            if let Some(pattern) = self.nodes[at].pattern {
+                matches.push((pattern, i + 1));
+            }
+        }
+        matches
+    }
+}
+
+fn count_arrangements(trie: &Trie, design: &[Color]) -> Vec<u128> {
+    // ways[i] is the number of ways to build design[i..], so ways[0] is the answer.
+    let mut ways = vec![0; design.len() + 1];
+    ways[design.len()] = 1;
+    for i in (0..design.len()).rev() {
+        ways[i] = trie
+            .matches_at(design, i)
+            .iter()
+            .map(|(_, len)| ways[i + len])
+            .sum();
+    }
+    ways
+}
+
+fn min_towels(trie: &Trie, design: &[Color]) -> Option<usize> {
+    let mut fewest: Vec<Option<usize>> = vec![None; design.len() + 1];
+    fewest[design.len()] = Some(0);
+    for i in (0..design.len()).rev() {
+        fewest[i] = trie
+            .matches_at(design, i)
+            .iter()
+            .filter_map(|(_, len)| fewest[i + len].map(|f| f + 1))
+            .min();
+    }
+    fewest[0]
+}
+
+struct Arrangements {
+    // Only the matches that lead to a finished design are kept, so the walk never backtracks out
+    // of a dead end.
+    options: Vec<Vec<(usize, usize)>>,
+    // Position in the design and which option was taken there, for each towel so far.
+    stack: Vec<(usize, usize)>,
+    done: bool,
+}
+
+impl Arrangements {
+    fn new(trie: &Trie, design: &[Color]) -> Self {
+        let ways = count_arrangements(trie, design);
+        let options = (0..design.len())
+            .map(|i| {
+                trie.matches_at(design, i)
+                    .into_iter()
+                    .filter(|(_, len)| ways[i + len] > 0)
+                    .collect()
+            })
+            .collect();
+        Self {
+            options,
+            stack: Vec::new(),
+            done: ways[0] == 0,
+        }
+    }
+
+    fn covered(&self) -> usize {
+        match self.stack.last() {
+            Some((at, option)) => at + self.options[*at][*option].1,
+            None => 0,
+        }
     }
-    for pattern in patterns {
-        if pattern.len() == design.len() && pattern == design {
-            // Last bug was still early aborting here with a return.
-            acc += 1;
+}
+
+impl Iterator for Arrangements {
+    // Pattern indices, in order.
+    type Item = Vec<usize>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+
+        if self.options.is_empty() {
+            // An empty design has exactly one arrangement, using no towels at all.
+            self.done = true;
+            return Some(Vec::new());
+        }
+
+        if self.stack.is_empty() {
+            // First time through, just take the first option everywhere.
+            self.stack.push((0, 0));
+        } else {
+            // Advance the deepest towel that has another option left, and drop the rest.
+            loop {
+                match self.stack.pop() {
+                    Some((at, option)) if option + 1 < self.options[at].len() => {
+                        self.stack.push((at, option + 1));
+                        break;
+                    }
+                    Some(_) => continue,
+                    None => {
+                        self.done = true;
+                        return None;
+                    }
+                }
+            }
+        }
+
+        let end = self.options.len();
+        while self.covered() < end {
+            self.stack.push((self.covered(), 0));
         }
-        if pattern.len() > design.len() {
+
+        Some(
+            self.stack
+                .iter()
+                .map(|(at, option)| self.options[*at][*option].0)
+                .collect(),
+        )
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct Diagnosis {
+    design: usize,
+    // Length of the longest prefix that can be built from the patterns.
+    longest_prefix: usize,
+    // Position of the first color that no pattern can reach past, from any buildable prefix.
+    stuck_at: usize,
+    // Smallest set of new patterns that would make the design buildable (see diagnose).
+    missing: Vec<Vec<Color>>,
+}
+
+fn is_buildable_with(trie: &Trie, design: &[Color], extra: &[Color]) -> bool {
+    let mut buildable = vec![false; design.len() + 1];
+    buildable[0] = true;
+    for i in 0..design.len() {
+        if !buildable[i] {
             continue;
         }
-        if !design.starts_with(pattern) {
+        for (_, len) in trie.matches_at(design, i) {
+            buildable[i + len] = true;
+        }
+        if design[i..].starts_with(extra) {
+            buildable[i + extra.len()] = true;
+        }
+    }
+    buildable[design.len()]
+}
+
+fn diagnose(trie: &Trie, design: &[Color]) -> Option<(usize, usize, Vec<Vec<Color>>)> {
+    let mut buildable = vec![false; design.len() + 1];
+    buildable[0] = true;
+    let mut stuck_at = 0;
+    for i in 0..design.len() {
+        if !buildable[i] {
             continue;
         }
-        acc += is_possible(patterns, &design[pattern.len()..]);
+        for (_, len) in trie.matches_at(design, i) {
+            buildable[i + len] = true;
+        }
+        // How far down the trie this start gets, whether or not a pattern ends there.
+        let mut at = 0;
+        let mut reach = i;
+        for color in &design[i..] {
+            match trie.nodes[at].children[color.index()] {
+                Some(next) => {
+                    at = next;
+                    reach += 1;
+                }
+                None => break,
+            }
+        }
+        stuck_at = stuck_at.max(reach);
     }
+    if buildable[design.len()] {
+        return None;
+    }
+    let longest_prefix = (0..=design.len()).rev().find(|i| buildable[*i]).unwrap();
+
+    // One new pattern is always enough, since the whole design would do. So the smallest set is
+    // the shortest piece of the design that fixes it on its own, which is often one that gets
+    // used more than once. Ties go to whichever comes first in the design.
+    let mut pieces: Vec<&[Color]> = (0..design.len())
+        .flat_map(|i| (i + 1..=design.len()).map(move |j| &design[i..j]))
+        .collect();
+    pieces.sort_by_key(|piece| piece.len());
+    let piece = pieces
+        .into_iter()
+        .find(|piece| is_buildable_with(trie, design, piece))
+        .unwrap();
+    let missing = vec![piece.to_vec()];
+
+    Some((longest_prefix, stuck_at, missing))
+}
 
-    acc
+fn diagnose_all(parsed: &State) -> Vec<Diagnosis> {
+    let trie = Trie::new(&parsed.patterns);
+    parsed
+        .designs
+        .iter()
+        .enumerate()
+        .filter_map(|(i, design)| {
+            diagnose(&trie, design).map(|(longest_prefix, stuck_at, missing)| Diagnosis {
+                design: i,
+                longest_prefix,
+                stuck_at,
+                missing,
+            })
+        })
+        .collect()
 }
 
-fn solve(parsed: &State) -> u64 {
-    let mut accumulator = 0;
-    for design in &parsed.designs {
-        accumulator += is_possible(&parsed.patterns, design);
+fn format_diagnoses(parsed: &State, diagnoses: &[Diagnosis]) -> String {
+    let mut report = String::new();
+    for d in diagnoses {
+        let design = &parsed.designs[d.design];
+        report.push_str(&format!(
+            "{} (design {}): builds up to {:?}, stuck at {} ({:?}), needs {}\n",
+            colors_to_string(design),
+            d.design,
+            colors_to_string(&design[..d.longest_prefix]),
+            d.stuck_at,
+            design
+                .get(d.stuck_at)
+                .map(|c| colors_to_string(&[*c]))
+                .unwrap_or_default(),
+            d.missing
+                .iter()
+                .map(|m| colors_to_string(m))
+                .collect::<Vec<_>>()
+                .join(", ")
+        ));
     }
-    accumulator
+    report
+}
+
+fn solve(parsed: &State) -> u128 {
+    let trie = Trie::new(&parsed.patterns);
+    parsed
+        .designs
+        .iter()
+        .map(|design| count_arrangements(&trie, design)[0])
+        .sum()
 }
 
 fn main() {
     let input = load_input(2024, 19);
     let parsed = parse_input(input);
+    if DIAGNOSE {
+        print!("{}", format_diagnoses(&parsed, &diagnose_all(&parsed)));
+    }
+    if let Some(n) = SHOW_ARRANGEMENTS {
+        let trie = Trie::new(&parsed.patterns);
+        for design in &parsed.designs {
+            let Some(fewest) = min_towels(&trie, design) else {
+                continue;
+            };
+            println!("{} (at least {} towels):", colors_to_string(design), fewest);
+            for arrangement in Arrangements::new(&trie, design).take(n) {
+                let towels: Vec<String> = arrangement
+                    .iter()
+                    .map(|i| colors_to_string(&parsed.patterns[*i]))
+                    .collect();
+                println!("    {}", towels.join(","));
+            }
+        }
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -152,23 +452,185 @@ bbrgwb
             parsed
         );
 
+        let trie = Trie::new(&parsed.patterns);
+
         // brwrr
-        assert_eq!(2, is_possible(&parsed.patterns, &parsed.designs[0]));
+        assert_eq!(2, count_arrangements(&trie, &parsed.designs[0])[0]);
         // bggr
-        assert_eq!(1, is_possible(&parsed.patterns, &parsed.designs[1]));
+        assert_eq!(1, count_arrangements(&trie, &parsed.designs[1])[0]);
         // gbbr
-        assert_eq!(4, is_possible(&parsed.patterns, &parsed.designs[2]));
+        assert_eq!(4, count_arrangements(&trie, &parsed.designs[2])[0]);
         // rrbgbr
-        assert_eq!(6, is_possible(&parsed.patterns, &parsed.designs[3]));
+        assert_eq!(6, count_arrangements(&trie, &parsed.designs[3])[0]);
         // ubwu
-        assert_eq!(0, is_possible(&parsed.patterns, &parsed.designs[4]));
+        assert_eq!(0, count_arrangements(&trie, &parsed.designs[4])[0]);
         // bwurrg
-        assert_eq!(1, is_possible(&parsed.patterns, &parsed.designs[5]));
+        assert_eq!(1, count_arrangements(&trie, &parsed.designs[5])[0]);
         // brgr
-        assert_eq!(2, is_possible(&parsed.patterns, &parsed.designs[6]));
+        assert_eq!(2, count_arrangements(&trie, &parsed.designs[6])[0]);
         // bbrgwb
-        assert_eq!(0, is_possible(&parsed.patterns, &parsed.designs[7]));
+        assert_eq!(0, count_arrangements(&trie, &parsed.designs[7])[0]);
 
         assert_eq!(16, solve(&parsed));
     }
+
+    #[test]
+    fn day19b_trie() {
+        let input = "
+r, wr, b, g, bwu, rb, gb, br
+
+brwrr
+bggr
+gbbr
+rrbgbr
+ubwu
+bwurrg
+brgr
+bbrgwb
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+        let trie = Trie::new(&parsed.patterns);
+
+        let counts: Vec<u128> = parsed
+            .designs
+            .iter()
+            .map(|d| count_arrangements(&trie, d)[0])
+            .collect();
+        assert_eq!(vec![2, 1, 4, 6, 0, 1, 2, 0], counts);
+
+        let fewest: Vec<Option<usize>> = parsed
+            .designs
+            .iter()
+            .map(|d| min_towels(&trie, d))
+            .collect();
+        assert_eq!(
+            vec![
+                Some(3),
+                Some(4),
+                Some(2),
+                Some(4),
+                None,
+                Some(4),
+                Some(3),
+                None
+            ],
+            fewest
+        );
+
+        // gbbr: g,b,b,r / g,b,br / gb,b,r / gb,br
+        let arrangements: Vec<Vec<usize>> = Arrangements::new(&trie, &parsed.designs[2]).collect();
+        assert_eq!(
+            vec![vec![3, 2, 2, 0], vec![3, 2, 7], vec![6, 2, 0], vec![6, 7]],
+            arrangements
+        );
+        // Nothing to build is one way of building it, with no towels.
+        assert_eq!(1, count_arrangements(&trie, &[])[0]);
+        assert_eq!(
+            vec![Vec::<usize>::new()],
+            Arrangements::new(&trie, &[]).collect::<Vec<_>>()
+        );
+        for design in &parsed.designs {
+            let count = count_arrangements(&trie, design)[0];
+            assert_eq!(count as usize, Arrangements::new(&trie, design).count());
+            for arrangement in Arrangements::new(&trie, design) {
+                let built: Vec<Color> = arrangement
+                    .iter()
+                    .flat_map(|i| parsed.patterns[*i].clone())
+                    .collect();
+                assert_eq!(*design, built);
+            }
+        }
+    }
+
+    #[test]
+    fn day19b_trie_no_overflow() {
+        // Every split of 150 whites, which is the 151st Fibonacci number and well past u64.
+        let parsed = parse_input(format!("w, ww\n\n{}", "w".repeat(150)));
+        let trie = Trie::new(&parsed.patterns);
+        assert_eq!(
+            16130531424904581415797907386349,
+            count_arrangements(&trie, &parsed.designs[0])[0]
+        );
+        assert_eq!(Some(75), min_towels(&trie, &parsed.designs[0]));
+        assert_eq!(
+            Some(vec![0; 150]),
+            Arrangements::new(&trie, &parsed.designs[0]).next()
+        );
+    }
+
+    #[test]
+    fn day19b_diagnose() {
+        let input = "
+r, wr, b, g, bwu, rb, gb, br
+
+brwrr
+bggr
+gbbr
+rrbgbr
+ubwu
+bwurrg
+brgr
+bbrgwb
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        let diagnoses = diagnose_all(&parsed);
+        assert_eq!(
+            vec![
+                Diagnosis {
+                    design: 4,
+                    longest_prefix: 0,
+                    stuck_at: 0,
+                    missing: vec![vec![Color::U]],
+                },
+                Diagnosis {
+                    design: 7,
+                    longest_prefix: 4,
+                    stuck_at: 5,
+                    missing: vec![vec![Color::W]],
+                },
+            ],
+            diagnoses
+        );
+        assert_eq!(
+            "ubwu (design 4): builds up to \"\", stuck at 0 (\"u\"), needs u\n\
+             bbrgwb (design 7): builds up to \"bbrg\", stuck at 5 (\"b\"), needs w\n",
+            format_diagnoses(&parsed, &diagnoses)
+        );
+
+        // Adding what's missing really does fix them.
+        let mut fixed =
+            parse_input("r, wr, b, g, bwu, rb, gb, br, u, w\n\nubwu\nbbrgwb".to_string());
+        assert!(diagnose_all(&fixed).is_empty());
+        fixed.patterns.truncate(8);
+        assert_eq!(2, diagnose_all(&fixed).len());
+
+        // The walk can get further than the longest prefix: bwr gets as far as the r.
+        let parsed = parse_input("b, bwu\n\nbwr".to_string());
+        let diagnoses = diagnose_all(&parsed);
+        assert_eq!(1, diagnoses[0].longest_prefix);
+        assert_eq!(2, diagnoses[0].stuck_at);
+        assert_eq!(vec![vec![Color::W, Color::R]], diagnoses[0].missing);
+
+        // Filling the gaps with w and r would cover the fewest colors, but wgr used twice is a
+        // single new pattern.
+        let parsed = parse_input("b, g\n\nwgrbwgr".to_string());
+        let diagnoses = diagnose_all(&parsed);
+        assert_eq!(
+            vec![vec![Color::W, Color::G, Color::R]],
+            diagnoses[0].missing
+        );
+
+        // Nothing shorter than the whole design fixes this one on its own.
+        let parsed = parse_input("b, g\n\nwrbw".to_string());
+        let diagnoses = diagnose_all(&parsed);
+        assert_eq!(
+            vec![vec![Color::W, Color::R, Color::B, Color::W]],
+            diagnoses[0].missing
+        );
+    }
 }
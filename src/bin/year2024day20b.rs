@@ -1,5 +1,5 @@
 use aoc_2024_rs::*;
-use rustc_hash::{FxHashMap, FxHashSet};
+use rustc_hash::FxHashMap;
 
 #[derive(Debug, PartialEq, Clone)]
 enum Tile {
@@ -53,22 +53,14 @@ fn parse_input(input: String) -> State {
     state
 }
 
-fn count_steps(state: &State) -> Vec<(Point2<i32>, i32)> {
-    let mut path = Vec::new();
-    let mut visited = FxHashSet::default();
-
-    let mut at = state.start_at;
-    let mut counter = -1;
-    loop {
-        counter += 1;
-        path.push((at, counter));
-
-        if at == state.end_at {
-            break;
-        }
-
-        visited.insert(at);
+fn distances(state: &State, from: &Point2<i32>) -> FxHashMap<Point2<i32>, i32> {
+    // Plain BFS, so it doesn't matter how many branches or dead ends the maze has.
+    let mut dists = FxHashMap::default();
+    dists.insert(*from, 0);
 
+    let mut queue = std::collections::VecDeque::from([*from]);
+    while let Some(at) = queue.pop_front() {
+        let cost = dists[&at];
         for d in [
             Direction::North,
             Direction::East,
@@ -76,27 +68,49 @@ fn count_steps(state: &State) -> Vec<(Point2<i32>, i32)> {
             Direction::West,
         ] {
             let candidate = d.step(&at);
-            if state.grid.get(&candidate) == Some(&Tile::Wall) {
+            if state.grid.get(&candidate) != Some(&Tile::Open) {
                 continue;
             }
-            if visited.contains(&candidate) {
+            if dists.contains_key(&candidate) {
                 continue;
             }
-            at = candidate;
-            break;
+            dists.insert(candidate, cost + 1);
+            queue.push_back(candidate);
         }
     }
 
-    path
+    dists
 }
 
-fn get_neighbors(state: &State, at: &Point2<i32>, radius: i32) -> Vec<Point2<i32>> {
+#[allow(dead_code)]
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum Metric {
+    Manhattan,
+    // Diagonal moves cost the same as straight ones.
+    Chebyshev,
+}
+
+impl Metric {
+    fn distance(&self, a: &Point2<i32>, b: &Point2<i32>) -> i32 {
+        match self {
+            Self::Manhattan => a.manhattan_distance(b),
+            Self::Chebyshev => a.chebyshev_distance(b),
+        }
+    }
+}
+
+fn get_neighbors(
+    state: &State,
+    at: &Point2<i32>,
+    radius: i32,
+    metric: &Metric,
+) -> Vec<Point2<i32>> {
     let mut neighbors = Vec::new();
 
     for x in (at.x - radius)..=(at.x + radius) {
         for y in (at.y - radius)..=(at.y + radius) {
             let candidate = Point2::new(x, y);
-            if at.manhattan_distance(&candidate) > radius {
+            if metric.distance(at, &candidate) > radius {
                 continue;
             }
             if !state.bbox.contains(&candidate) {
@@ -111,56 +125,106 @@ fn get_neighbors(state: &State, at: &Point2<i32>, radius: i32) -> Vec<Point2<i32
 
 /// Return a map of (seconds-saved, count).
 fn find_cheats(state: &State, radius: i32) -> FxHashMap<i32, i32> {
-    let start_at = state.start_at;
-    let end_at = state.end_at;
+    find_cheats_with(state, radius, &Metric::Manhattan)
+}
+
+/// Return a map of (seconds-saved, count), for any maze and any way of measuring the cheat.
+fn find_cheats_with(state: &State, radius: i32, metric: &Metric) -> FxHashMap<i32, i32> {
+    let mut saved = FxHashMap::default();
+    for cheat in list_cheats(state, radius, metric) {
+        saved
+            .entry(cheat.saved)
+            .and_modify(|c| *c += 1)
+            .or_insert(1);
+    }
+    saved
+}
 
-    // Similar approach as in day 16. Get cost to each cell on the path both forward and backward,
-    // then (start, node) and (node, end) sum to the total cost for any given point on the path.
-    // This time, use Manhattan distance to fill in the jump cost.
-    let mut state = state.clone();
-    let forward_dists = count_steps(&state);
-    let forward_dists_lookup: FxHashMap<Point2<i32>, i32> =
-        forward_dists.clone().into_iter().collect();
-    state.start_at = end_at;
-    state.end_at = start_at;
-    let backward_dists_lookup: FxHashMap<Point2<i32>, i32> =
-        count_steps(&state).into_iter().collect();
+#[derive(Debug, PartialEq, Eq, Clone)]
+struct Cheat {
+    start: Point2<i32>,
+    end: Point2<i32>,
+    length: i32,
+    saved: i32,
+}
 
-    let max_cost: i32 = forward_dists_lookup.len().try_into().unwrap();
+/// Return every cheat that saves any time, biggest savings first.
+fn list_cheats(state: &State, radius: i32, metric: &Metric) -> Vec<Cheat> {
+    // Similar approach as in day 16. Get cost to each cell both forward and backward, then
+    // (start, node) and (node, end) sum to the total cost for any given point. The cheat itself
+    // costs whatever the metric says the jump is. A cheat is its (start, end) pair, so every pair
+    // is only looked at once.
+    let forward_dists = distances(state, &state.start_at);
+    let backward_dists = distances(state, &state.end_at);
 
-    let mut visited = FxHashSet::default();
+    let Some(honest_cost) = forward_dists.get(&state.end_at) else {
+        return Vec::new();
+    };
 
-    let mut saved = FxHashMap::default();
+    let mut cheats = Vec::new();
     for (p, forward_cost) in &forward_dists {
-        visited.insert(p);
-        for n in get_neighbors(&state, p, radius) {
-            // Can't end the cheat on a wall.
-            if state.grid.get(&n) == Some(&Tile::Wall) {
-                continue;
-            }
-            // Don't want to end up on a previous point on the original path.
-            if visited.contains(&n) {
+        for n in get_neighbors(state, p, radius, metric) {
+            // Can't end the cheat on a wall, or anywhere the end can't be reached from.
+            let Some(backward_cost) = backward_dists.get(&n) else {
                 continue;
-            }
-
-            // Looking at (start, end) jumps uniquely, so "the same cheat" definition didn't need
-            // to be explicitly checked or de-duped.
+            };
 
-            let backward_cost = backward_dists_lookup.get(&n).unwrap();
-            let traverse_cost = p.manhattan_distance(&n);
-            let cost = forward_cost + backward_cost + traverse_cost;
-            // Including start in the direction dists, so subtract one.
-            let save = max_cost - 1 - cost;
+            let length = metric.distance(p, &n);
+            let save = honest_cost - (forward_cost + length + backward_cost);
 
             if save <= 0 {
                 continue;
             }
 
-            saved.entry(save).and_modify(|c| *c += 1).or_insert(1);
+            cheats.push(Cheat {
+                start: *p,
+                end: n,
+                length,
+                saved: save,
+            });
         }
     }
 
-    saved
+    cheats.sort_by_key(|c| (-c.saved, c.start, c.end));
+    cheats
+}
+
+#[allow(dead_code)]
+fn cheats_saving_at_least(cheats: &[Cheat], save_at_least: i32) -> Vec<Cheat> {
+    cheats
+        .iter()
+        .filter(|c| c.saved >= save_at_least)
+        .cloned()
+        .collect()
+}
+
+#[allow(dead_code)]
+fn pprint_cheat(state: &State, cheat: &Cheat) -> String {
+    // Cheat start is '1' and end is '2', drawn over anything else.
+    let mut s = String::new();
+    for y in state.bbox.min.y..=state.bbox.max.y {
+        for x in state.bbox.min.x..=state.bbox.max.x {
+            let p = Point2::new(x, y);
+            let c = if p == cheat.start {
+                '1'
+            } else if p == cheat.end {
+                '2'
+            } else if p == state.start_at {
+                'S'
+            } else if p == state.end_at {
+                'E'
+            } else {
+                match state.grid.get(&p) {
+                    Some(Tile::Wall) => '#',
+                    Some(Tile::Open) => '.',
+                    None => unreachable!("Grid is malformed!"),
+                }
+            };
+            s.push(c);
+        }
+        s.push('\n');
+    }
+    s.trim().to_string()
 }
 
 fn solve(parsed: &State, save_at_least: i32) -> i32 {
@@ -217,15 +281,15 @@ mod tests {
         assert_eq!(Some(&Tile::Wall), parsed.grid.get(&parsed.bbox.min));
         assert_eq!(Some(&Tile::Wall), parsed.grid.get(&parsed.bbox.max));
 
-        let steps = count_steps(&parsed);
-        assert_eq!(84 + 1, steps.len());
-        assert_eq!((parsed.start_at, 0), steps[0]);
-        assert_eq!((Direction::North.step(&parsed.start_at), 1), steps[1]);
-        assert_eq!((parsed.end_at, 84), steps[steps.len() - 1]);
+        let dists = distances(&parsed, &parsed.start_at);
+        assert_eq!(84 + 1, dists.len());
+        assert_eq!(Some(&0), dists.get(&parsed.start_at));
         assert_eq!(
-            (Direction::West.step(&parsed.end_at), 83),
-            steps[steps.len() - 2]
+            Some(&1),
+            dists.get(&Direction::North.step(&parsed.start_at))
         );
+        assert_eq!(Some(&84), dists.get(&parsed.end_at));
+        assert_eq!(Some(&83), dists.get(&Direction::West.step(&parsed.end_at)));
 
         // Mixed up what the keys and values were a few times here.
         let count_saved = FxHashMap::from_iter([
@@ -255,4 +319,138 @@ mod tests {
         assert_eq!(0, solve(&parsed, i32::MAX));
         assert_eq!(count_saved.values().sum::<i32>(), solve(&parsed, 50));
     }
+
+    #[test]
+    fn day20b_branching_maze() {
+        // Two ways round to the end, which a walk down a single corridor would get wrong.
+        let input = "
+#######
+#S#E..#
+#.#.#.#
+#.....#
+#######
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        assert_eq!(
+            Some(&6),
+            distances(&parsed, &parsed.start_at).get(&parsed.end_at)
+        );
+
+        assert_eq!(
+            FxHashMap::from_iter([(4, 1), (2, 1)]),
+            find_cheats_with(&parsed, 2, &Metric::Manhattan)
+        );
+        assert_eq!(
+            FxHashMap::from_iter([(4, 1), (3, 2), (2, 3), (1, 6)]),
+            find_cheats_with(&parsed, 2, &Metric::Chebyshev)
+        );
+    }
+
+    #[test]
+    fn day20b_single_track_radius_2() {
+        let input = "
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        // Same numbers as part a.
+        assert_eq!(
+            FxHashMap::from_iter([
+                (2, 14),
+                (4, 14),
+                (6, 2),
+                (8, 4),
+                (10, 2),
+                (12, 3),
+                (20, 1),
+                (36, 1),
+                (38, 1),
+                (40, 1),
+                (64, 1),
+            ]),
+            find_cheats(&parsed, 2)
+        );
+    }
+
+    #[test]
+    fn day20b_list_cheats() {
+        let input = "
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..E#...#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+        "
+        .trim()
+        .to_string();
+        let parsed = parse_input(input);
+
+        let cheats = list_cheats(&parsed, 2, &Metric::Manhattan);
+        assert_eq!(44, cheats.len());
+        assert_eq!(
+            14,
+            cheats_saving_at_least(&cheats, 2).len() - cheats_saving_at_least(&cheats, 3).len()
+        );
+        assert_eq!(
+            vec![Cheat {
+                start: Point2::new(7, 7),
+                end: Point2::new(5, 7),
+                length: 2,
+                saved: 64,
+            }],
+            cheats_saving_at_least(&cheats, 64)
+        );
+
+        assert_eq!(
+            "
+###############
+#...#...#.....#
+#.#.#.#.#.###.#
+#S#...#.#.#...#
+#######.#.#.###
+#######.#.#...#
+#######.#.###.#
+###..2#1..#...#
+###.#######.###
+#...###...#...#
+#.#####.#.###.#
+#.#...#.#.#...#
+#.#.#.#.#.#.###
+#...#...#...###
+###############
+            "
+            .trim()
+            .to_string(),
+            pprint_cheat(&parsed, &cheats[0])
+        );
+    }
 }
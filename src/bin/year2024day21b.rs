@@ -1,7 +1,10 @@
 use std::collections::HashMap;
 
 use aoc_2024_rs::*;
-use cached::proc_macro::cached;
+
+// Type every code out in full through this many robots, then check that the presses really type
+// it without any arm going over a gap. Sequences grow about 2.5x per robot, so keep it small.
+const CHECK_DEPTH: Option<usize> = None;
 
 #[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
 enum Button {
@@ -33,8 +36,27 @@ fn parse_input(input: String) -> Vec<Vec<Button>> {
         .collect()
 }
 
-// These two keypad() definitions below are considered different types with lazy_static.
-// Also don't get code formatting inside the lazy_static block?
+type Keypad = HashMap<Point2<i32>, Button>;
+
+/// Each line is a row of keys, one char per key, with '#' marking a gap.
+fn parse_keypad(diagram: &str) -> Keypad {
+    let mut keypad = HashMap::new();
+    for (y, line) in diagram.trim().lines().enumerate() {
+        for (x, ch) in line.trim().chars().enumerate() {
+            if ch == '#' {
+                continue;
+            }
+            let p = Point2::new(x.try_into().unwrap(), y.try_into().unwrap());
+            if keypad.insert(p, Button::from(ch)).is_some() {
+                panic!("Duplicate key position: {:?}", p);
+            }
+        }
+    }
+    if keypad.values().filter(|b| **b == Button::Activate).count() != 1 {
+        panic!("Keypad needs exactly one A key: {:?}", diagram);
+    }
+    keypad
+}
 
 /// ```
 /// +---+---+---+
@@ -47,20 +69,15 @@ fn parse_input(input: String) -> Vec<Vec<Button>> {
 ///     | 0 | A |
 ///     +---+---+
 /// ```
-fn numeric_keypad() -> HashMap<Point2<i32>, Button> {
-    HashMap::from([
-        (Point2::new(2, 0), Button::Digit(9)),
-        (Point2::new(1, 0), Button::Digit(8)),
-        (Point2::new(0, 0), Button::Digit(7)),
-        (Point2::new(2, 1), Button::Digit(6)),
-        (Point2::new(1, 1), Button::Digit(5)),
-        (Point2::new(0, 1), Button::Digit(4)),
-        (Point2::new(2, 2), Button::Digit(3)),
-        (Point2::new(1, 2), Button::Digit(2)),
-        (Point2::new(0, 2), Button::Digit(1)),
-        (Point2::new(2, 3), Button::Activate),
-        (Point2::new(1, 3), Button::Digit(0)),
-    ])
+fn numeric_keypad() -> Keypad {
+    parse_keypad(
+        "
+789
+456
+123
+#0A
+        ",
+    )
 }
 
 /// ```
@@ -70,14 +87,13 @@ fn numeric_keypad() -> HashMap<Point2<i32>, Button> {
 /// | < | v | > |
 /// +---+---+---+
 /// ```
-fn directional_keypad() -> HashMap<Point2<i32>, Button> {
-    HashMap::from([
-        (Point2::new(2, 0), Button::Activate),
-        (Point2::new(1, 0), Button::Direction(Direction::North)),
-        (Point2::new(2, 1), Button::Direction(Direction::East)),
-        (Point2::new(1, 1), Button::Direction(Direction::South)),
-        (Point2::new(0, 1), Button::Direction(Direction::West)),
-    ])
+fn directional_keypad() -> Keypad {
+    parse_keypad(
+        "
+#^A
+<v>
+        ",
+    )
 }
 
 fn get_position(keypad: &HashMap<Point2<i32>, Button>, button: &Button) -> Point2<i32> {
@@ -144,6 +160,33 @@ fn get_paths(
     )
 }
 
+fn button_to_char(button: &Button) -> char {
+    match button {
+        Button::Digit(d) => char::from_digit((*d).try_into().unwrap(), 10).unwrap(),
+        Button::Activate => 'A',
+        Button::Direction(Direction::North) => '^',
+        Button::Direction(Direction::East) => '>',
+        Button::Direction(Direction::South) => 'v',
+        Button::Direction(Direction::West) => '<',
+    }
+}
+
+/// Every shortest way to press end after start, as the presses on the keypad above it.
+fn get_options(keypad: &Keypad, start: &Button, end: &Button) -> Vec<Vec<Button>> {
+    if start == end {
+        return vec![vec![Button::Activate]];
+    }
+    get_paths(keypad, start, end)
+        .iter()
+        .map(|path| {
+            path.iter()
+                .map(|(_, d)| Button::Direction(*d))
+                .chain([Button::Activate])
+                .collect()
+        })
+        .collect()
+}
+
 // <vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A
 // v<<A>>^A<A>AvA<^AA>A<vAAA>^A
 // <A^A>^^AvvvA
@@ -154,48 +197,187 @@ fn get_paths(
 // 2(>):   v <<   A >>  ^ A   <   A > A  v  A   <  ^ AA > A   < v  AAA >  ^ A
 // 3(>): <vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A
 
-#[cached(
-    key = "String",
-    convert = r#"{ format!("{:?} {:?}", buttons, depth) }"#
-)]
-fn get_min_seq(keypad: &HashMap<Point2<i32>, Button>, buttons: &[Button], depth: usize) -> usize {
+type SeqCostCache = Memo<(Vec<Button>, usize), usize>;
+
+/// Fewest top-level presses to type buttons on keypad, through depth robots (every one but the
+/// last at an inner keypad). Only the inner keypad's costs are cached, since it's the only one
+/// that gets asked about more than once.
+fn seq_cost(
+    keypad: &Keypad,
+    inner: &Keypad,
+    buttons: &[Button],
+    depth: usize,
+    cache: &mut SeqCostCache,
+) -> usize {
     if depth == 0 {
         return buttons.len();
     }
 
-    let mut seq = buttons.to_owned();
-    seq.insert(0, Button::Activate);
-
     let mut size = 0;
+    let mut start = Button::Activate;
+    for end in buttons {
+        let (_, cost) = cheapest_option(keypad, inner, &start, end, depth, cache);
+        size += cost;
+        start = *end;
+    }
+    size
+}
 
-    let mut pairs = seq.windows(2);
-    while let Some([start, end]) = pairs.next() {
-        size += if start == end {
-            1
-        } else {
-            get_paths(keypad, start, end)
-                .iter()
-                .map(|path| {
-                    get_min_seq(
-                        &directional_keypad(),
-                        &path
-                            .iter()
-                            .map(|(_, d)| Button::Direction(*d))
-                            .chain(vec![Button::Activate])
-                            .collect::<Vec<Button>>(),
-                        depth - 1,
-                    )
-                })
-                .min()
-                .unwrap()
-        };
+fn cheapest_option(
+    keypad: &Keypad,
+    inner: &Keypad,
+    start: &Button,
+    end: &Button,
+    depth: usize,
+    cache: &mut SeqCostCache,
+) -> (Vec<Button>, usize) {
+    // Ties go to whichever option get_paths found first, which tries north, east, south, west in
+    // that order at every step, so the choice is always the same.
+    let mut best: Option<(Vec<Button>, usize)> = None;
+    for option in get_options(keypad, start, end) {
+        let cost = cache.get_or_compute((option.clone(), depth - 1), |cache| {
+            seq_cost(inner, inner, &option, depth - 1, cache)
+        });
+        if best.as_ref().is_none_or(|(_, b)| cost < *b) {
+            best = Some((option, cost));
+        }
+    }
+    best.unwrap()
+}
+
+/// Lazily walks the optimal top-level key sequence for typing buttons on keypad, through depth
+/// layers of inner keypads. Only one pair per layer is expanded at a time, so depth 25 is fine.
+struct KeySequence {
+    keypad: Keypad,
+    inner: Keypad,
+    cache: SeqCostCache,
+    // (buttons on this layer, next index, previous button, depth), outermost first.
+    frames: Vec<(Vec<Button>, usize, Button, usize)>,
+}
+
+impl KeySequence {
+    fn new(keypad: &Keypad, inner: &Keypad, buttons: &[Button], depth: usize) -> Self {
+        Self {
+            keypad: keypad.clone(),
+            inner: inner.clone(),
+            cache: Memo::new(),
+            frames: vec![(buttons.to_vec(), 0, Button::Activate, depth)],
+        }
     }
 
-    size
+    fn len(&mut self) -> usize {
+        let (buttons, _, _, depth) = self.frames[0].clone();
+        seq_cost(&self.keypad, &self.inner, &buttons, depth, &mut self.cache)
+    }
+}
+
+impl Iterator for KeySequence {
+    type Item = Button;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let outermost = self.frames.len() == 1;
+            let (buttons, i, prev, depth) = self.frames.last_mut()?;
+            if *i == buttons.len() {
+                self.frames.pop();
+                continue;
+            }
+
+            let end = buttons[*i];
+            *i += 1;
+            if *depth == 0 {
+                return Some(end);
+            }
+
+            let start = *prev;
+            *prev = end;
+            let depth = *depth;
+            let keypad = if outermost { &self.keypad } else { &self.inner };
+            let (option, _) =
+                cheapest_option(keypad, &self.inner, &start, &end, depth, &mut self.cache);
+            self.frames.push((option, 0, Button::Activate, depth - 1));
+        }
+    }
+}
+
+/// Run-length encodes any stream of buttons, e.g. "<<<A" as [(<, 3), (A, 1)].
+fn run_lengths(buttons: impl Iterator<Item = Button>) -> Vec<(Button, usize)> {
+    let mut runs: Vec<(Button, usize)> = Vec::new();
+    for button in buttons {
+        match runs.last_mut() {
+            Some((b, count)) if *b == button => *count += 1,
+            _ => runs.push((button, 1)),
+        }
+    }
+    runs
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct GapError {
+    // Index into the top-level presses of the one that did it.
+    press: usize,
+    // Which robot's arm went over the gap, 0 being the one nearest the top-level presses.
+    layer: usize,
+    at: Point2<i32>,
+}
+
+/// Runs top-level presses through depth robots, the last of which is at keypad and the rest of
+/// which are at inner keypads, and returns whatever gets typed on keypad.
+fn simulate(
+    keypad: &Keypad,
+    inner: &Keypad,
+    presses: &[Button],
+    depth: usize,
+) -> Result<Vec<Button>, GapError> {
+    let keypads: Vec<&Keypad> = (0..depth)
+        .map(|layer| if layer + 1 == depth { keypad } else { inner })
+        .collect();
+    let mut arms: Vec<Point2<i32>> = keypads
+        .iter()
+        .map(|k| get_position(k, &Button::Activate))
+        .collect();
+
+    let mut typed = Vec::new();
+    for (press, button) in presses.iter().enumerate() {
+        // Follow the press down through the robots until it either moves an arm or comes out
+        // the bottom as a typed button.
+        let mut button = *button;
+        let mut layer = 0;
+        loop {
+            if layer == depth {
+                typed.push(button);
+                break;
+            }
+            match button {
+                Button::Direction(d) => {
+                    arms[layer] = d.step(&arms[layer]);
+                    if !keypads[layer].contains_key(&arms[layer]) {
+                        return Err(GapError {
+                            press,
+                            layer,
+                            at: arms[layer],
+                        });
+                    }
+                    break;
+                }
+                Button::Activate => {
+                    button = keypads[layer][&arms[layer]];
+                    layer += 1;
+                }
+                Button::Digit(_) => panic!("Can't press a digit on a directional keypad!"),
+            }
+        }
+    }
+
+    Ok(typed)
 }
 
 fn solve(parsed: &Vec<Vec<Button>>, depth: usize) -> usize {
+    let numeric = numeric_keypad();
+    let directional = directional_keypad();
+
     let mut accumulator = 0;
+    let mut cache = Memo::new();
 
     for buttons in parsed {
         let numeric_part: usize = buttons
@@ -207,7 +389,7 @@ fn solve(parsed: &Vec<Vec<Button>>, depth: usize) -> usize {
             .fold(0, |acc, d| acc * 10 + d)
             .try_into()
             .unwrap();
-        let min_size = get_min_seq(&numeric_keypad(), buttons, depth);
+        let min_size = seq_cost(&numeric, &directional, buttons, depth, &mut cache);
         accumulator += min_size * numeric_part;
     }
 
@@ -217,6 +399,29 @@ fn solve(parsed: &Vec<Vec<Button>>, depth: usize) -> usize {
 fn main() {
     let input = load_input(2024, 21);
     let parsed = parse_input(input);
+    if let Some(depth) = CHECK_DEPTH {
+        let numeric = numeric_keypad();
+        let directional = directional_keypad();
+        for buttons in &parsed {
+            let mut seq = KeySequence::new(&numeric, &directional, buttons, depth);
+            let len = seq.len();
+            let presses: Vec<Button> = seq.collect();
+            assert_eq!(len, presses.len());
+            match simulate(&numeric, &directional, &presses, depth) {
+                Ok(typed) => assert_eq!(*buttons, typed),
+                Err(e) => panic!("Typing {:?} went over a gap: {:?}", buttons, e),
+            }
+            let code: String = buttons.iter().map(button_to_char).collect();
+            let runs: String = run_lengths(presses.into_iter())
+                .iter()
+                .map(|(b, count)| match count {
+                    1 => button_to_char(b).to_string(),
+                    _ => format!("{}{}", button_to_char(b), count),
+                })
+                .collect();
+            println!("{}: {} presses, {}", code, len, runs);
+        }
+    }
     let answer = solve(&parsed, 26);
     println!("Answer: {:?}", answer);
 }
@@ -330,6 +535,9 @@ mod tests {
         .to_string();
         let parsed = parse_input(input);
 
+        let numeric = numeric_keypad();
+        let directional = directional_keypad();
+
         // <vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A
         // v<<A>>^A<A>AvA<^AA>A<vAAA>^A
         // <A^A>^^AvvvA
@@ -337,25 +545,195 @@ mod tests {
 
         assert_eq!(
             "<A^A>^^AvvvA".len(),
-            get_min_seq(&numeric_keypad(), &parsed[0], 1)
+            seq_cost(&numeric, &directional, &parsed[0], 1, &mut Memo::new())
         );
         assert_eq!(
             "v<<A>>^A<A>AvA<^AA>A<vAAA>^A".len(),
-            get_min_seq(&numeric_keypad(), &parsed[0], 2)
+            seq_cost(&numeric, &directional, &parsed[0], 2, &mut Memo::new())
         );
         assert_eq!(
             "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A".len(),
-            get_min_seq(&numeric_keypad(), &parsed[0], 3)
+            seq_cost(&numeric, &directional, &parsed[0], 3, &mut Memo::new())
         );
 
         // 68 * 29, 60 * 980, 68 * 179, 64 * 456, and 64 * 379
 
-        assert_eq!(68, get_min_seq(&numeric_keypad(), &parsed[0], 3));
-        assert_eq!(60, get_min_seq(&numeric_keypad(), &parsed[1], 3));
-        assert_eq!(68, get_min_seq(&numeric_keypad(), &parsed[2], 3));
-        assert_eq!(64, get_min_seq(&numeric_keypad(), &parsed[3], 3));
-        assert_eq!(64, get_min_seq(&numeric_keypad(), &parsed[4], 3));
+        assert_eq!(
+            68,
+            seq_cost(&numeric, &directional, &parsed[0], 3, &mut Memo::new())
+        );
+        assert_eq!(
+            60,
+            seq_cost(&numeric, &directional, &parsed[1], 3, &mut Memo::new())
+        );
+        assert_eq!(
+            68,
+            seq_cost(&numeric, &directional, &parsed[2], 3, &mut Memo::new())
+        );
+        assert_eq!(
+            64,
+            seq_cost(&numeric, &directional, &parsed[3], 3, &mut Memo::new())
+        );
+        assert_eq!(
+            64,
+            seq_cost(&numeric, &directional, &parsed[4], 3, &mut Memo::new())
+        );
 
         assert_eq!(126384, solve(&parsed, 3));
     }
+
+    #[test]
+    fn day21b_parse_keypad() {
+        assert_eq!(
+            HashMap::from([
+                (Point2::new(2, 0), Button::Digit(9)),
+                (Point2::new(1, 0), Button::Digit(8)),
+                (Point2::new(0, 0), Button::Digit(7)),
+                (Point2::new(2, 1), Button::Digit(6)),
+                (Point2::new(1, 1), Button::Digit(5)),
+                (Point2::new(0, 1), Button::Digit(4)),
+                (Point2::new(2, 2), Button::Digit(3)),
+                (Point2::new(1, 2), Button::Digit(2)),
+                (Point2::new(0, 2), Button::Digit(1)),
+                (Point2::new(2, 3), Button::Activate),
+                (Point2::new(1, 3), Button::Digit(0)),
+            ]),
+            numeric_keypad()
+        );
+        assert_eq!(
+            HashMap::from([
+                (Point2::new(2, 0), Button::Activate),
+                (Point2::new(1, 0), Button::Direction(Direction::North)),
+                (Point2::new(2, 1), Button::Direction(Direction::East)),
+                (Point2::new(1, 1), Button::Direction(Direction::South)),
+                (Point2::new(0, 1), Button::Direction(Direction::West)),
+            ]),
+            directional_keypad()
+        );
+    }
+
+    #[test]
+    fn day21b_key_sequence() {
+        let parsed = parse_input("029A 980A 179A 456A 379A".to_string());
+        let numeric = numeric_keypad();
+        let directional = directional_keypad();
+
+        for buttons in &parsed {
+            for depth in 0..=3 {
+                let mut seq = KeySequence::new(&numeric, &directional, buttons, depth);
+                assert_eq!(seq.len(), seq.count());
+            }
+        }
+
+        let seq: String = KeySequence::new(&numeric, &directional, &parsed[0], 1)
+            .map(|b| button_to_char(&b))
+            .collect();
+        // Ties go north first, so this isn't quite the sequence from the puzzle text.
+        assert_eq!("<A^A^^>AvvvA", seq);
+        let again: String = KeySequence::new(&numeric, &directional, &parsed[0], 1)
+            .map(|b| button_to_char(&b))
+            .collect();
+        assert_eq!(seq, again);
+
+        assert_eq!(
+            vec![
+                (Button::Direction(Direction::West), 1),
+                (Button::Activate, 1),
+                (Button::Direction(Direction::North), 1),
+                (Button::Activate, 1),
+                (Button::Direction(Direction::North), 2),
+                (Button::Direction(Direction::East), 1),
+                (Button::Activate, 1),
+                (Button::Direction(Direction::South), 3),
+                (Button::Activate, 1),
+            ],
+            run_lengths(KeySequence::new(&numeric, &directional, &parsed[0], 1))
+        );
+
+        // Far too long to ever materialize, but the start of it is right there.
+        let seq = KeySequence::new(&numeric, &directional, &parsed[0], 26);
+        assert_eq!(1000, seq.take(1000).count());
+
+        // A different keypad works the same way.
+        let phone = parse_keypad(
+            "
+123
+456
+789
+#0A
+            ",
+        );
+        let mut seq = KeySequence::new(&phone, &directional, &parsed[0], 1);
+        assert_eq!(12, seq.len());
+        assert_eq!(
+            "<A^^^A>vvAvA",
+            KeySequence::new(&phone, &directional, &parsed[0], 1)
+                .map(|b| button_to_char(&b))
+                .collect::<String>()
+        );
+    }
+
+    #[test]
+    fn day21b_simulate() {
+        let parsed = parse_input("029A 980A 179A 456A 379A".to_string());
+        let numeric = numeric_keypad();
+        let directional = directional_keypad();
+
+        let presses = parse_input(
+            "<vA<AA>>^AvAA<^A>A<v<A>>^AvA^A<vA>^A<v<A>^A>AAvA^A<v<A>A>^AAAvA<^A>A".to_string(),
+        );
+        assert_eq!(
+            Ok(parsed[0].clone()),
+            simulate(&numeric, &directional, &presses[0], 3)
+        );
+        assert_eq!(
+            Ok(parse_input("<A^A>^^AvvvA".to_string())[0].clone()),
+            simulate(&directional, &directional, &presses[0], 2)
+        );
+
+        for buttons in &parsed {
+            for depth in 0..=4 {
+                let presses: Vec<Button> =
+                    KeySequence::new(&numeric, &directional, buttons, depth).collect();
+                assert_eq!(
+                    Ok(buttons.clone()),
+                    simulate(&numeric, &directional, &presses, depth)
+                );
+            }
+        }
+
+        // Straight left from A on the numeric keypad runs into the gap on the second press.
+        let presses = parse_input("<<A".to_string());
+        assert_eq!(
+            Err(GapError {
+                press: 1,
+                layer: 0,
+                at: Point2::new(0, 3),
+            }),
+            simulate(&numeric, &directional, &presses[0], 1)
+        );
+
+        // With another robot in front, the same thing takes a few more presses and happens one
+        // layer down.
+        let presses = parse_input("v<<AA".to_string());
+        assert_eq!(
+            Err(GapError {
+                press: 4,
+                layer: 1,
+                at: Point2::new(0, 3),
+            }),
+            simulate(&numeric, &directional, &presses[0], 2)
+        );
+
+        // Or the first robot wanders into the gap on its own keypad.
+        let presses = parse_input("<<".to_string());
+        assert_eq!(
+            Err(GapError {
+                press: 1,
+                layer: 0,
+                at: Point2::new(0, 0),
+            }),
+            simulate(&numeric, &directional, &presses[0], 2)
+        );
+    }
 }
@@ -7,20 +7,101 @@ fn parse_input(input: String) -> Vec<u64> {
         .collect()
 }
 
+const PRUNE: u64 = 16777216;
+
+// Run every buyer's final secret back 2000 rounds and check it lands on their first one.
+const CHECK_UNEVOLVE: bool = false;
+// Check evolution shuffles all 2^24 secrets and print its cycles (slow outside of release).
+const SHOW_CYCLES: bool = false;
+
 fn evolve(value: u64, rounds: u64) -> u64 {
     let mut v = value;
 
-    let prune = 16777216;
+    for _ in 0..rounds {
+        v = ((v * 64) ^ v).rem_euclid(PRUNE);
+        v = ((v / 32) ^ v).rem_euclid(PRUNE);
+        v = ((v * 2048) ^ v).rem_euclid(PRUNE);
+    }
+
+    v
+}
+
+fn unevolve(value: u64, rounds: u64) -> u64 {
+    // Each step is an xorshift on 24 bits. Multiplying by 64 is a shift left by 6 and dividing
+    // by 32 is a shift right by 5, and y = x ^ (x << k) can be undone by feeding the known low
+    // bits back in, k more bits at a time, until all 24 are recovered (same for right shifts,
+    // from the top down).
+    fn unshift_left(y: u64, k: u32) -> u64 {
+        let mut x = y;
+        for _ in 0..24_u32.div_ceil(k) {
+            x = (y ^ (x << k)) & (PRUNE - 1);
+        }
+        x
+    }
+    fn unshift_right(y: u64, k: u32) -> u64 {
+        let mut x = y;
+        for _ in 0..24_u32.div_ceil(k) {
+            x = y ^ (x >> k);
+        }
+        x
+    }
+
+    let mut v = value.rem_euclid(PRUNE);
 
     for _ in 0..rounds {
-        v = ((v * 64) ^ v).rem_euclid(prune);
-        v = ((v / 32) ^ v).rem_euclid(prune);
-        v = ((v * 2048) ^ v).rem_euclid(prune);
+        v = unshift_left(v, 11);
+        v = unshift_right(v, 5);
+        v = unshift_left(v, 6);
     }
 
     v
 }
 
+fn cycle_length(seed: u64) -> u64 {
+    // Evolution is a permutation, so every seed is on a cycle, and following it must come back
+    // round to the seed itself.
+    let seed = seed.rem_euclid(PRUNE);
+    let mut v = evolve(seed, 1);
+    let mut length = 1;
+    while v != seed {
+        v = evolve(v, 1);
+        length += 1;
+    }
+    length
+}
+
+fn is_bijection() -> bool {
+    let mut hit = vec![false; PRUNE as usize];
+    for v in 0..PRUNE {
+        let next = evolve(v, 1) as usize;
+        if hit[next] {
+            return false;
+        }
+        hit[next] = true;
+    }
+    true
+}
+
+/// Return every distinct cycle length with how many cycles have it, shortest first.
+fn cycle_structure() -> Vec<(u64, u64)> {
+    let mut visited = vec![false; PRUNE as usize];
+    let mut counts: std::collections::BTreeMap<u64, u64> = std::collections::BTreeMap::new();
+    for seed in 0..PRUNE {
+        if visited[seed as usize] {
+            continue;
+        }
+        let mut v = seed;
+        let mut length = 0;
+        while !visited[v as usize] {
+            visited[v as usize] = true;
+            v = evolve(v, 1);
+            length += 1;
+        }
+        *counts.entry(length).or_insert(0) += 1;
+    }
+    counts.into_iter().collect()
+}
+
 fn solve(parsed: &[u64]) -> u64 {
     parsed.iter().map(|start| evolve(*start, 2000)).sum()
 }
@@ -28,6 +109,20 @@ fn solve(parsed: &[u64]) -> u64 {
 fn main() {
     let input = load_input(2024, 22);
     let parsed = parse_input(input);
+    if CHECK_UNEVOLVE {
+        for seed in &parsed {
+            assert_eq!(*seed, unevolve(evolve(*seed, 2000), 2000));
+        }
+    }
+    if SHOW_CYCLES {
+        assert!(is_bijection());
+        for (length, count) in cycle_structure() {
+            println!("{} cycle(s) of length {}", count, length);
+        }
+        if let Some(seed) = parsed.first() {
+            println!("{} comes back round after {}", seed, cycle_length(*seed));
+        }
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -70,4 +165,38 @@ mod tests {
 
         assert_eq!(37327623, solve(&parsed));
     }
+
+    #[test]
+    fn day22a_unevolve() {
+        let seq = vec![
+            123, 15887950, 16495136, 527345, 704524, 1553684, 12683156, 11100544, 12249484,
+            7753432, 5908254,
+        ];
+        for i in 1..seq.len() {
+            assert_eq!(seq[i - 1], unevolve(seq[i], 1));
+        }
+        assert_eq!(123, unevolve(5908254, 10));
+
+        for (start, end) in [
+            (1, 8685429),
+            (10, 4700978),
+            (100, 15273692),
+            (2024, 8667524),
+        ] {
+            assert_eq!(start, unevolve(end, 2000));
+        }
+    }
+
+    #[test]
+    #[ignore = "walks all 2^24 secrets, run with --release --ignored"]
+    fn day22a_cycles() {
+        // Zero never goes anywhere, everything else is on one big cycle.
+        assert_eq!(1, cycle_length(0));
+        assert_eq!(PRUNE - 1, cycle_length(123));
+
+        assert!(is_bijection());
+        let structure = cycle_structure();
+        assert_eq!(vec![(1, 1), (PRUNE - 1, 1)], structure);
+        assert_eq!(PRUNE, structure.iter().map(|(l, c)| l * c).sum::<u64>());
+    }
 }
@@ -1,5 +1,4 @@
 use aoc_2024_rs::*;
-use rustc_hash::{FxHashMap, FxHashSet};
 
 fn parse_input(input: String) -> Vec<u64> {
     input
@@ -24,7 +23,101 @@ fn evolve(value: u64, rounds: u64) -> Vec<u64> {
     steps
 }
 
-fn solve(parsed: &Vec<u64>, rounds: u64) -> u64 {
+// Each change is in -9..=9, so a 4-change sequence is a 4 digit base 19 number. That gives a
+// dense index into a flat array, which is a lot cheaper than hashing tuples.
+//
+// Why 21? Not sure what the relationship is here. This accumulator has 40951 entries at the
+// end. The average seen length is 1930 across all rounds. There are 2044 inputs. 40951 / 2044
+// is 20.03... I'm not sure if it really makes any difference to reserve or not here though.
+//
+// Since each 4-change sequence is anchored to an existing index in the changes, and the
+// changes are each rounds - 1 in length, the patholgical case of every 4-change being unique
+// would produce 2044 * (2000 - 1) entries (4M), much higher than the ~41k here. Reserving that
+// much is definitely overkill and slows down the runtime. So all this must just be an artifact
+// of how the inputs were created, and 21 isn't a "valid" coefficient.
+//
+// The array sidesteps guessing: every possible sequence gets a slot, and 19^4 (~130k) is only a
+// few times the ~41k that actually show up, nowhere near the 4M worst case.
+const SEQUENCES: usize = 19 * 19 * 19 * 19;
+
+// Splitting the seeds across threads is optional, 1 just runs everything on the current thread.
+const THREADS: usize = 4;
+
+// How many of the best sequences to print, along with what each buyer paid for them.
+const SHOW_TOP: Option<usize> = None;
+
+#[allow(dead_code)]
+fn encode(seq: (i64, i64, i64, i64)) -> usize {
+    let (a, b, c, d) = seq;
+    [a, b, c, d].iter().fold(0, |index, change| {
+        index * 19 + usize::try_from(change + 9).unwrap()
+    })
+}
+
+fn decode(index: usize) -> (i64, i64, i64, i64) {
+    let digit = |place: u32| i64::try_from(index / 19_usize.pow(place) % 19).unwrap() - 9;
+    (digit(3), digit(2), digit(1), digit(0))
+}
+
+/// Call `f` with the sequence index and the price for every 4-change sequence the first time it
+/// shows up in this seed's evolution. `last_seen` must be SEQUENCES long and `stamp` must be
+/// different from anything already written into it (e.g. buyer number + 1).
+fn for_each_first(
+    seed: u64,
+    rounds: u64,
+    last_seen: &mut [u32],
+    stamp: u32,
+    mut f: impl FnMut(usize, u64),
+) {
+    let evolutions = evolve(seed, rounds);
+    let mut index = 0;
+    for i in 1..evolutions.len() {
+        let a = evolutions[i - 1].rem_euclid(10);
+        let b = evolutions[i].rem_euclid(10);
+        // Rolling base 19 window, the oldest change falls off the top.
+        index = (index * 19 + usize::try_from(b + 9 - a).unwrap()) % SEQUENCES;
+        if i < 4 || last_seen[index] == stamp {
+            continue;
+        }
+        last_seen[index] = stamp;
+        f(index, b);
+    }
+}
+
+fn accumulate(seeds: &[u64], rounds: u64) -> Vec<u64> {
+    let mut totals = vec![0; SEQUENCES];
+    let mut last_seen = vec![0; SEQUENCES];
+    for (buyer, seed) in seeds.iter().enumerate() {
+        let stamp = u32::try_from(buyer + 1).unwrap();
+        for_each_first(*seed, rounds, &mut last_seen, stamp, |index, price| {
+            totals[index] += price;
+        });
+    }
+    totals
+}
+
+fn get_totals(parsed: &[u64], rounds: u64, threads: usize) -> Vec<u64> {
+    if threads <= 1 || parsed.len() < threads {
+        return accumulate(parsed, rounds);
+    }
+
+    let chunk_size = parsed.len().div_ceil(threads);
+    std::thread::scope(|scope| {
+        let handles: Vec<_> = parsed
+            .chunks(chunk_size)
+            .map(|chunk| scope.spawn(move || accumulate(chunk, rounds)))
+            .collect();
+        let mut totals = vec![0; SEQUENCES];
+        for handle in handles {
+            for (total, part) in totals.iter_mut().zip(handle.join().unwrap()) {
+                *total += part;
+            }
+        }
+        totals
+    })
+}
+
+fn solve(parsed: &[u64], rounds: u64) -> u64 {
     // Implemented this in a new function at first, didn't update the call here, got answer WAY too
     // high (when using the previous part's scoring).
 
@@ -32,59 +125,68 @@ fn solve(parsed: &Vec<u64>, rounds: u64) -> u64 {
     // is seen in a paritcular evolution, add that resulting price to the accumulator, and then sum
     // with the subsequent prices in later evolutions (but still only the first time that 4-change
     // is seen per evolution). The highest accumulated price at the end is the answer.
+    solve_with(parsed, rounds, THREADS)
+}
 
-    let mut accumulator: FxHashMap<(i64, i64, i64, i64), u64> = FxHashMap::default();
-    // Why 21? Not sure what the relationship is here. This accumulator has 40951 entries at the
-    // end. The average seen length is 1930 across all rounds. There are 2044 inputs. 40951 / 2044
-    // is 20.03... I'm not sure if it really makes any difference to reserve or not here though.
-    //
-    // Since each 4-change sequence is anchored to an existing index in the changes, and the
-    // changes are each rounds - 1 in length, the patholgical case of every 4-change being unique
-    // would produce 2044 * (2000 - 1) entries (4M), much higher than the ~41k here. Reserving that
-    // much is definitely overkill and slows down the runtime. So all this must just be an artifact
-    // of how the inputs were created, and 21 isn't a "valid" coefficient.
-    accumulator.reserve(parsed.len() * 21);
-
-    let mut seen = FxHashSet::default();
-    seen.reserve(parsed.len());
-
-    for seed in parsed {
-        let evolutions = evolve(*seed, rounds);
-        let mut changes: Vec<(u64, i64)> = Vec::new();
-        for i in 1..evolutions.len() {
-            let a = evolutions[i - 1].rem_euclid(10);
-            let b = evolutions[i].rem_euclid(10);
-            let price = b;
-            let change: i64 = i64::try_from(b).unwrap() - i64::try_from(a).unwrap();
-            changes.push((price, change));
-        }
+fn solve_with(parsed: &[u64], rounds: u64, threads: usize) -> u64 {
+    get_totals(parsed, rounds, threads)
+        .into_iter()
+        .max()
+        .unwrap_or(0)
+}
 
-        seen.clear();
-        for i in 3..changes.len() {
-            let (_, ac) = changes[i - 3];
-            let (_, bc) = changes[i - 2];
-            let (_, cc) = changes[i - 1];
-            let (dp, dc) = changes[i];
-            let seq = (ac, bc, cc, dc);
-            // Only want the first time this 4-change is seen in this evolution.
-            if !seen.insert(seq) {
-                continue;
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Ranked {
+    sequence: (i64, i64, i64, i64),
+    total: u64,
+    // Price each buyer sold at for this sequence, in input order (None if it never showed up).
+    contributions: Vec<Option<u64>>,
+}
+
+fn top_sequences(parsed: &[u64], rounds: u64, n: usize, threads: usize) -> Vec<Ranked> {
+    let totals = get_totals(parsed, rounds, threads);
+
+    let mut order: Vec<usize> = (0..SEQUENCES).filter(|i| totals[*i] > 0).collect();
+    order.sort_by(|a, b| totals[*b].cmp(&totals[*a]).then(a.cmp(b)));
+    order.truncate(n);
+
+    // Only need to know which of the top ones to track, so map sequence index to rank.
+    let mut rank_of = vec![usize::MAX; SEQUENCES];
+    for (rank, index) in order.iter().enumerate() {
+        rank_of[*index] = rank;
+    }
+
+    let mut ranked: Vec<Ranked> = order
+        .iter()
+        .map(|index| Ranked {
+            sequence: decode(*index),
+            total: totals[*index],
+            contributions: vec![None; parsed.len()],
+        })
+        .collect();
+
+    let mut last_seen = vec![0; SEQUENCES];
+    for (buyer, seed) in parsed.iter().enumerate() {
+        let stamp = u32::try_from(buyer + 1).unwrap();
+        for_each_first(*seed, rounds, &mut last_seen, stamp, |index, price| {
+            if rank_of[index] != usize::MAX {
+                ranked[rank_of[index]].contributions[buyer] = Some(price);
             }
-            accumulator
-                .entry(seq)
-                .and_modify(|c| *c += dp)
-                .or_insert(dp);
-        }
+        });
     }
 
-    let mut max_price = 0;
-    for (_, price) in accumulator {
-        if price > max_price {
-            max_price = price;
+    ranked
+}
+
+fn pprint_top(parsed: &[u64], ranked: &[Ranked]) {
+    for (rank, r) in ranked.iter().enumerate() {
+        println!("#{} {:?} -> {}", rank + 1, r.sequence, r.total);
+        for (seed, price) in parsed.iter().zip(r.contributions.iter()) {
+            if let Some(price) = price {
+                println!("    {}: {}", seed, price);
+            }
         }
     }
-
-    max_price
 }
 
 fn main() {
@@ -92,6 +194,9 @@ fn main() {
     let parsed = parse_input(input);
     let answer = solve(&parsed, 2000);
     println!("Answer: {:?}", answer);
+    if let Some(n) = SHOW_TOP {
+        pprint_top(&parsed, &top_sequences(&parsed, 2000, n, THREADS));
+    }
 }
 
 #[cfg(test)]
@@ -149,4 +254,38 @@ mod tests {
 
         assert_eq!(7 + 7 + 9, solve(&parsed, 2000));
     }
+
+    #[test]
+    fn day22b_top_sequences() {
+        let parsed = vec![1, 2, 3, 2024];
+
+        assert_eq!((-2, 1, -1, 3), decode(encode((-2, 1, -1, 3))));
+        assert_eq!((-9, -9, -9, -9), decode(0));
+        assert_eq!((9, 9, 9, 9), decode(SEQUENCES - 1));
+
+        for threads in [1, 2, 3, 4] {
+            assert_eq!(23, solve_with(&parsed, 2000, threads));
+        }
+
+        let top = top_sequences(&parsed, 2000, 3, 2);
+        assert_eq!(3, top.len());
+        assert_eq!(
+            Ranked {
+                sequence: (-2, 1, -1, 3),
+                total: 23,
+                contributions: vec![Some(7), Some(7), None, Some(9)],
+            },
+            top[0]
+        );
+        assert!(top[1].total <= top[0].total && top[2].total <= top[1].total);
+        for r in top.iter() {
+            assert_eq!(r.total, r.contributions.iter().flatten().sum::<u64>());
+        }
+
+        // The third buyer really does sell at 0 for this one, which isn't the same as the first
+        // buyer never seeing it.
+        let all = top_sequences(&parsed, 2000, usize::MAX, 2);
+        let r = all.iter().find(|r| r.sequence == (0, 2, 0, -2)).unwrap();
+        assert_eq!(vec![None, Some(6), Some(0), None], r.contributions);
+    }
 }
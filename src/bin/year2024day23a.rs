@@ -1,7 +1,10 @@
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashSet, VecDeque};
 
 use aoc_2024_rs::*;
 
+// Print a summary of the whole network (as text and JSON) after the answer.
+const REPORT: bool = false;
+
 fn parse_input(input: String) -> Vec<(String, String)> {
     input
         .split_whitespace()
@@ -14,31 +17,15 @@ fn parse_input(input: String) -> Vec<(String, String)> {
         .collect()
 }
 
-fn get_direct_connections(connections: &Vec<(String, String)>) -> HashMap<String, HashSet<String>> {
-    // All the one-hop directly connected machines. Example:
-    // "aq": {"vc", "yn", "cg", "wq"},
-
-    let mut groups: HashMap<String, HashSet<String>> = HashMap::new();
-
+fn get_graph(connections: &[(String, String)]) -> Graph {
+    let mut graph = Graph::undirected();
     for (a, b) in connections {
-        groups
-            .entry(a.clone())
-            .and_modify(|children| {
-                children.insert(b.clone());
-            })
-            .or_insert(HashSet::from([b.clone()]));
-        groups
-            .entry(b.clone())
-            .and_modify(|children| {
-                children.insert(a.clone());
-            })
-            .or_insert(HashSet::from([a.clone()]));
+        graph.add_edge(a, b);
     }
-
-    groups
+    graph
 }
 
-fn get_groups_of_three(connections: &Vec<(String, String)>) -> HashSet<(String, String, String)> {
+fn get_groups_of_three(connections: &[(String, String)]) -> HashSet<(String, String, String)> {
     // Example of direct connections involving "aq":
     //
     // "aq": {"vc", "yn", "cg", "wq"},
@@ -48,29 +35,31 @@ fn get_groups_of_three(connections: &Vec<(String, String)>) -> HashSet<(String,
     // "vc": {"ub", "aq", "wq", "tb"},
     //
     // Take aq,vc then find {vc,yn,cq,wq} ∩ {ub,aq,wq,tb} = {wq}, so aq,vc,wq is one 3-group.
+    //
+    // Each triangle is only visited once, as a < b < c.
 
     let mut groups = HashSet::new();
 
-    let direct_connections = get_direct_connections(connections);
-    for (lparent, lchildren) in &direct_connections {
-        for rparent in lchildren {
-            let rchildren = direct_connections.get(rparent).unwrap();
-            for mchild in lchildren.intersection(rchildren) {
-                let mut group = [lparent, rparent, mchild];
-                group.sort();
-                groups.insert((
-                    group[0].to_string(),
-                    group[1].to_string(),
-                    group[2].to_string(),
-                ));
+    let graph = get_graph(connections);
+    for (a, b) in graph.edges() {
+        for c in graph.neighbors(b).iter().filter(|c| **c > b) {
+            if !graph.has_edge(a, *c) {
+                continue;
             }
+            let mut group = [graph.name(a), graph.name(b), graph.name(*c)];
+            group.sort();
+            groups.insert((
+                group[0].to_string(),
+                group[1].to_string(),
+                group[2].to_string(),
+            ));
         }
     }
 
     groups
 }
 
-fn solve(parsed: &Vec<(String, String)>) -> usize {
+fn solve(parsed: &[(String, String)]) -> usize {
     get_groups_of_three(parsed)
         .iter()
         .filter(|group| {
@@ -79,11 +68,168 @@ fn solve(parsed: &Vec<(String, String)>) -> usize {
         .count()
 }
 
+#[derive(Debug, Clone, PartialEq)]
+struct GraphStats {
+    nodes: usize,
+    edges: usize,
+    // Degree -> how many machines have it.
+    degrees: BTreeMap<usize, usize>,
+    triangles: usize,
+    // Mean of each machine's local clustering (how many of its neighbour pairs are connected).
+    average_clustering: f64,
+    // Global version: closed triples over all connected triples.
+    transitivity: f64,
+    // Sizes of the connected components, largest first.
+    components: Vec<usize>,
+    // The shared degree, if every machine has the same one.
+    regular: Option<usize>,
+}
+
+impl GraphStats {
+    fn to_text(&self) -> String {
+        let mut lines = vec![
+            format!("nodes: {}", self.nodes),
+            format!("edges: {}", self.edges),
+            "degrees:".to_string(),
+        ];
+        for (degree, count) in &self.degrees {
+            lines.push(format!("  {}: {}", degree, count));
+        }
+        lines.push(format!("triangles: {}", self.triangles));
+        lines.push(format!(
+            "average clustering: {:.4}",
+            self.average_clustering
+        ));
+        lines.push(format!("transitivity: {:.4}", self.transitivity));
+        lines.push(format!(
+            "components: {} {:?}",
+            self.components.len(),
+            self.components
+        ));
+        lines.push(match self.regular {
+            Some(degree) => format!("regular: yes (degree {})", degree),
+            None => "regular: no".to_string(),
+        });
+        lines.join("\n")
+    }
+
+    fn to_json(&self) -> String {
+        // Everything is a number, so no need to pull in a crate for escaping.
+        let degrees: Vec<String> = self
+            .degrees
+            .iter()
+            .map(|(degree, count)| format!("\"{}\":{}", degree, count))
+            .collect();
+        let components: Vec<String> = self.components.iter().map(|c| c.to_string()).collect();
+        format!(
+            "{{\"nodes\":{},\"edges\":{},\"degrees\":{{{}}},\"triangles\":{},\"average_clustering\":{},\"transitivity\":{},\"components\":[{}],\"regular\":{}}}",
+            self.nodes,
+            self.edges,
+            degrees.join(","),
+            self.triangles,
+            self.average_clustering,
+            self.transitivity,
+            components.join(","),
+            match self.regular {
+                Some(degree) => degree.to_string(),
+                None => "null".to_string(),
+            }
+        )
+    }
+}
+
+fn get_graph_stats(graph: &Graph) -> GraphStats {
+    let mut degrees = BTreeMap::new();
+    for id in graph.nodes() {
+        *degrees.entry(graph.degree(id)).or_insert(0) += 1;
+    }
+
+    // Triangles through each machine, i.e. connected pairs of its neighbours.
+    let local_triangles: Vec<usize> = graph
+        .nodes()
+        .map(|id| {
+            graph
+                .neighbors(id)
+                .iter()
+                .map(|a| {
+                    graph
+                        .neighbors(id)
+                        .iter()
+                        .filter(|b| *b > a && graph.has_edge(*a, **b))
+                        .count()
+                })
+                .sum()
+        })
+        .collect();
+
+    let mut clustering = 0.0;
+    let mut triples = 0;
+    for id in graph.nodes() {
+        let degree = graph.degree(id);
+        let pairs = degree * degree.saturating_sub(1) / 2;
+        triples += pairs;
+        if pairs > 0 {
+            clustering += local_triangles[id as usize] as f64 / pairs as f64;
+        }
+    }
+    let triangles = local_triangles.iter().sum::<usize>() / 3;
+
+    let mut seen = vec![false; graph.len()];
+    let mut components = Vec::new();
+    for start in graph.nodes() {
+        if seen[start as usize] {
+            continue;
+        }
+        seen[start as usize] = true;
+        let mut size = 0;
+        let mut queue = VecDeque::from([start]);
+        while let Some(id) = queue.pop_front() {
+            size += 1;
+            for next in graph.neighbors(id) {
+                if !seen[*next as usize] {
+                    seen[*next as usize] = true;
+                    queue.push_back(*next);
+                }
+            }
+        }
+        components.push(size);
+    }
+    components.sort_by(|a, b| b.cmp(a));
+
+    GraphStats {
+        nodes: graph.len(),
+        edges: graph.edges().count(),
+        regular: if degrees.len() == 1 {
+            degrees.keys().next().copied()
+        } else {
+            None
+        },
+        degrees,
+        triangles,
+        average_clustering: if graph.is_empty() {
+            0.0
+        } else {
+            clustering / graph.len() as f64
+        },
+        transitivity: if triples == 0 {
+            0.0
+        } else {
+            (3 * triangles) as f64 / triples as f64
+        },
+        components,
+    }
+}
+
 fn main() {
     let input = load_input(2024, 23);
     let parsed = parse_input(input);
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
+    if REPORT {
+        let stats = get_graph_stats(&get_graph(&parsed));
+        println!("{}", stats.to_text());
+        println!("{}", stats.to_json());
+    }
 }
 
 #[cfg(test)]
@@ -177,10 +323,18 @@ td-yn
             assert!(actual.contains(&expected_item));
         }
 
-        let candidate = get_direct_connections(&parsed);
-        println!("{:?}", candidate);
+        let graph = get_graph(&parsed);
+        assert_eq!(16, graph.len());
+        let aq = graph.id("aq").unwrap();
+        let mut neighbors: Vec<&str> = graph
+            .neighbors(aq)
+            .iter()
+            .map(|id| graph.name(*id))
+            .collect();
+        neighbors.sort();
+        assert_eq!(vec!["cg", "vc", "wq", "yn"], neighbors);
         for _ in 0..50 {
-            assert_eq!(candidate, get_direct_connections(&parsed));
+            assert_eq!(graph, get_graph(&parsed));
         }
 
         let candidate = get_groups_of_three(&parsed);
@@ -190,5 +344,42 @@ td-yn
 
         assert_eq!(7, solve(&parsed));
     }
-}
 
+    #[test]
+    fn day23a_graph_stats() {
+        let stats = get_graph_stats(&get_graph(&parse_input(
+            "
+kh-tc qp-kh de-cg ka-co yn-aq qp-ub cg-tb vc-aq tb-ka wh-tc yn-cg kh-ub ta-co de-co tc-td tb-wq
+wh-td ta-ka td-qp aq-cg wq-ub ub-vc de-ta wq-aq wq-vc wh-yn ka-de kh-ta co-tc wh-qp tb-vc td-yn
+            "
+            .trim()
+            .to_string(),
+        )));
+
+        assert_eq!(16, stats.nodes);
+        assert_eq!(32, stats.edges);
+        assert_eq!(BTreeMap::from([(4, 16)]), stats.degrees);
+        assert_eq!(Some(4), stats.regular);
+        assert_eq!(12, stats.triangles);
+        assert_eq!(vec![16], stats.components);
+        assert_eq!(0.375, stats.transitivity);
+        assert_eq!(stats.transitivity, stats.average_clustering);
+        assert!(stats.to_text().ends_with("regular: yes (degree 4)"));
+        assert_eq!(
+            "{\"nodes\":16,\"edges\":32,\"degrees\":{\"4\":16},\"triangles\":12,\"average_clustering\":0.375,\"transitivity\":0.375,\"components\":[16],\"regular\":4}",
+            stats.to_json()
+        );
+
+        // A triangle with a tail, plus a lone pair.
+        let stats = get_graph_stats(&get_graph(&parse_input(
+            "aa-bb bb-cc cc-aa cc-dd ee-ff".to_string(),
+        )));
+        assert_eq!(None, stats.regular);
+        assert_eq!(1, stats.triangles);
+        assert_eq!(vec![4, 2], stats.components);
+        assert_eq!(0.6, stats.transitivity);
+        assert!(stats
+            .to_json()
+            .ends_with("\"components\":[4,2],\"regular\":null}"));
+    }
+}
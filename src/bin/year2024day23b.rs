@@ -1,5 +1,3 @@
-use std::collections::{HashMap, HashSet};
-
 use aoc_2024_rs::*;
 
 fn parse_input(input: String) -> Vec<(String, String)> {
@@ -14,97 +12,191 @@ fn parse_input(input: String) -> Vec<(String, String)> {
         .collect()
 }
 
-fn get_direct_connections(connections: &Vec<(String, String)>) -> HashMap<String, HashSet<String>> {
-    // All the one-hop directly connected machines. Example:
-    // "aq": {"vc", "yn", "cg", "wq"},
-
-    let mut groups: HashMap<String, HashSet<String>> = HashMap::new();
-
+fn get_graph(connections: &[(String, String)]) -> Graph {
+    let mut graph = Graph::undirected();
     for (a, b) in connections {
-        groups
-            .entry(a.clone())
-            .and_modify(|children| {
-                children.insert(b.clone());
-            })
-            .or_insert(HashSet::from([b.clone()]));
-        groups
-            .entry(b.clone())
-            .and_modify(|children| {
-                children.insert(a.clone());
-            })
-            .or_insert(HashSet::from([a.clone()]));
+        graph.add_edge(a, b);
     }
-
-    groups
+    graph
 }
 
-fn get_largest_local_group(
-    parent: &String,
-    direct_connections: &HashMap<String, HashSet<String>>,
-) -> HashSet<String> {
-    // For some reason I could only think of set operations, then of course
-    // HashMap<HashSet<String>, usize> or equivalent doesn't seem to work out of the box.
+fn degeneracy_order(graph: &Graph) -> Vec<u32> {
+    // Keep peeling off whichever machine has the fewest remaining connections. Starting the
+    // search from this order bounds how big the candidate sets can get.
+    //
+    // Machines sit in a bucket per remaining degree, and get pushed again into the lower bucket
+    // when a neighbour goes. Anything popped that's already gone, or has moved down since, is
+    // just skipped.
+    let mut degrees: Vec<usize> = graph.nodes().map(|id| graph.degree(id)).collect();
+    let mut buckets: Vec<Vec<u32>> = vec![Vec::new(); degrees.iter().max().map_or(0, |d| d + 1)];
+    for id in graph.nodes() {
+        buckets[degrees[id as usize]].push(id);
+    }
 
-    let first: HashSet<String> = direct_connections.get(parent).unwrap().clone();
-    let mut first_ordered: Vec<String> = first.iter().cloned().collect();
-    first_ordered.sort();
+    let mut removed = vec![false; graph.len()];
+    let mut order = Vec::with_capacity(graph.len());
+    let mut lowest = 0;
+    while order.len() < graph.len() {
+        let Some(next) = buckets[lowest].pop() else {
+            lowest += 1;
+            continue;
+        };
+        if removed[next as usize] || degrees[next as usize] != lowest {
+            continue;
+        }
+        removed[next as usize] = true;
+        order.push(next);
+        for neighbor in graph.neighbors(next) {
+            let n = *neighbor as usize;
+            if !removed[n] {
+                degrees[n] -= 1;
+                buckets[degrees[n]].push(*neighbor);
+                lowest = lowest.min(degrees[n]);
+            }
+        }
+    }
+    order
+}
 
-    // Get a list of connections that each child shares with this parent. Excluding the two parents
-    // themselves, as that produces the wrong result.
-    let rest: Vec<HashSet<String>> = first_ordered
+fn bron_kerbosch(
+    graph: &Graph,
+    clique: &mut Vec<u32>,
+    mut candidates: Vec<u32>,
+    mut excluded: Vec<u32>,
+    visit: &mut impl FnMut(&[u32]),
+) {
+    if candidates.is_empty() {
+        if excluded.is_empty() {
+            visit(clique);
+        }
+        return;
+    }
+
+    // Pivot on whoever covers the most candidates, then only branch on the ones it doesn't cover
+    // (any clique containing a covered one could also be grown with the pivot).
+    let pivot = *candidates
         .iter()
-        .map(|child| {
-            first
-                .intersection(direct_connections.get(child).unwrap())
-                .cloned()
-                .collect()
+        .chain(excluded.iter())
+        .max_by_key(|u| {
+            candidates
+                .iter()
+                .filter(|v| graph.has_edge(**u, **v))
+                .count()
         })
+        .unwrap();
+    let branches: Vec<u32> = candidates
+        .iter()
+        .filter(|v| !graph.has_edge(pivot, **v))
+        .copied()
         .collect();
 
-    // Find the most common subset of connections.
-    let mut max_count = 0;
-    let mut max_index = 0;
-    for i in 0..rest.len() {
-        let mut count = 0;
-        for j in (i + 1)..rest.len() {
-            if rest[i] == rest[j] {
-                count += 1;
-                if count > max_count {
-                    max_count = count;
-                    max_index = i;
-                }
-            }
-        }
+    for v in branches {
+        let keep = |w: &&u32| graph.has_edge(v, **w);
+        clique.push(v);
+        bron_kerbosch(
+            graph,
+            clique,
+            candidates.iter().filter(keep).copied().collect(),
+            excluded.iter().filter(keep).copied().collect(),
+            visit,
+        );
+        clique.pop();
+        candidates.retain(|w| *w != v);
+        excluded.push(v);
+    }
+}
+
+fn for_each_maximal_clique(graph: &Graph, mut visit: impl FnMut(&[u32])) {
+    let order = degeneracy_order(graph);
+    let mut rank = vec![0; graph.len()];
+    for (i, id) in order.iter().enumerate() {
+        rank[*id as usize] = i;
+    }
+    for v in order.iter() {
+        let (later, earlier): (Vec<u32>, Vec<u32>) = graph
+            .neighbors(*v)
+            .iter()
+            .partition(|u| rank[**u as usize] > rank[*v as usize]);
+        bron_kerbosch(graph, &mut vec![*v], later, earlier, &mut visit);
     }
+}
 
-    // Complete the connectivity group by adding the parents of the chosen connections.
-    let mut group = rest[max_index].clone();
-    group.insert(parent.to_string());
-    group.insert(first_ordered[max_index].to_string());
+fn to_names(graph: &Graph, clique: &[u32]) -> Vec<String> {
+    let mut names: Vec<String> = clique
+        .iter()
+        .map(|id| graph.name(*id).to_string())
+        .collect();
+    names.sort();
+    names
+}
 
-    group
+/// Every maximal clique with at least `size` machines, each sorted by name, in name order.
+#[allow(dead_code)]
+fn maximal_cliques_at_least(graph: &Graph, size: usize) -> Vec<Vec<String>> {
+    let mut cliques = Vec::new();
+    for_each_maximal_clique(graph, |clique| {
+        if clique.len() >= size {
+            cliques.push(to_names(graph, clique));
+        }
+    });
+    cliques.sort();
+    cliques
 }
 
-fn get_largest_global_group(connections: &Vec<(String, String)>) -> HashSet<String> {
-    let direct_connections = get_direct_connections(connections);
+/// Every clique that's as large as any in the graph (there can be more than one).
+fn maximum_cliques(graph: &Graph) -> Vec<Vec<String>> {
+    let mut best = 0;
+    let mut cliques = Vec::new();
+    for_each_maximal_clique(graph, |clique| {
+        if clique.len() > best {
+            best = clique.len();
+            cliques.clear();
+        }
+        if clique.len() == best {
+            cliques.push(to_names(graph, clique));
+        }
+    });
+    cliques.sort();
+    cliques
+}
 
-    let mut max_count = 0;
-    let mut max_group = HashSet::new();
-    for parent in direct_connections.keys() {
-        let group = get_largest_local_group(parent, &direct_connections);
-        if group.len() > max_count {
-            max_count = group.len();
-            max_group = group;
+/// How many (not necessarily maximal) cliques of exactly `k` machines there are.
+#[allow(dead_code)]
+fn count_k_cliques(graph: &Graph, k: usize) -> usize {
+    // Only extend towards machines later in the degeneracy order, so each clique is counted once.
+    fn extend(graph: &Graph, rank: &[usize], candidates: &[u32], k: usize) -> usize {
+        if k == 0 {
+            return 1;
         }
+        candidates
+            .iter()
+            .map(|v| {
+                let next: Vec<u32> = candidates
+                    .iter()
+                    .filter(|u| rank[**u as usize] > rank[*v as usize] && graph.has_edge(*v, **u))
+                    .copied()
+                    .collect();
+                extend(graph, rank, &next, k - 1)
+            })
+            .sum()
     }
 
-    max_group
+    let order = degeneracy_order(graph);
+    let mut rank = vec![0; graph.len()];
+    for (i, id) in order.iter().enumerate() {
+        rank[*id as usize] = i;
+    }
+    extend(graph, &rank, &order, k)
 }
 
-fn solve(parsed: &Vec<(String, String)>) -> String {
-    let mut group: Vec<String> = get_largest_global_group(parsed).iter().cloned().collect();
-    group.sort();
-    group.join(",")
+fn solve(parsed: &[(String, String)]) -> String {
+    // The password is the largest group of machines all connected to each other. If there's a
+    // tie, the first alphabetically wins, since the cliques come back sorted.
+    let graph = get_graph(parsed);
+    match maximum_cliques(&graph).first() {
+        Some(clique) => clique.join(","),
+        None => String::new(),
+    }
 }
 
 fn main() {
@@ -168,5 +260,111 @@ td-yn
 
         assert_eq!("co,de,ka,ta".to_string(), solve(&parsed));
     }
-}
 
+    #[test]
+    fn day23b_cliques() {
+        let input = "
+kh-tc
+qp-kh
+de-cg
+ka-co
+yn-aq
+qp-ub
+cg-tb
+vc-aq
+tb-ka
+wh-tc
+yn-cg
+kh-ub
+ta-co
+de-co
+tc-td
+tb-wq
+wh-td
+ta-ka
+td-qp
+aq-cg
+wq-ub
+ub-vc
+de-ta
+wq-aq
+wq-vc
+wh-yn
+ka-de
+kh-ta
+co-tc
+wh-qp
+tb-vc
+td-yn
+        "
+        .trim()
+        .to_string();
+        let graph = get_graph(&parse_input(input));
+
+        assert_eq!(16, graph.len());
+        assert_eq!(vec![vec!["co", "de", "ka", "ta"]], maximum_cliques(&graph));
+
+        assert_eq!(16, count_k_cliques(&graph, 1));
+        assert_eq!(32, count_k_cliques(&graph, 2));
+        assert_eq!(12, count_k_cliques(&graph, 3));
+        assert_eq!(1, count_k_cliques(&graph, 4));
+        assert_eq!(0, count_k_cliques(&graph, 5));
+
+        let cliques = maximal_cliques_at_least(&graph, 3);
+        assert_eq!(9, cliques.len());
+        assert!(cliques.contains(&vec![
+            "co".to_string(),
+            "de".to_string(),
+            "ka".to_string(),
+            "ta".to_string()
+        ]));
+        assert!(!cliques.contains(&vec!["co".to_string(), "de".to_string(), "ka".to_string()]));
+    }
+
+    #[test]
+    fn day23b_two_maximum_cliques() {
+        // The hub is in a triangle with each pair, but only the two K4s are the biggest groups.
+        let input = "
+aa-ab aa-ac aa-ad ab-ac ab-ad ac-ad
+ba-bb ba-bc ba-bd bb-bc bb-bd bc-bd
+hub-aa hub-ba hub-ab
+        "
+        .trim()
+        .to_string();
+        let graph = get_graph(&parse_input(input));
+
+        assert_eq!(
+            vec![vec!["aa", "ab", "ac", "ad"], vec!["ba", "bb", "bc", "bd"]],
+            maximum_cliques(&graph)
+        );
+
+        // Each machine peeled off has the fewest connections left of any that remain.
+        let order = degeneracy_order(&graph);
+        assert_eq!(graph.len(), order.len());
+        let mut removed = vec![false; graph.len()];
+        for id in order {
+            let remaining = |v: u32| {
+                graph
+                    .neighbors(v)
+                    .iter()
+                    .filter(|n| !removed[**n as usize])
+                    .count()
+            };
+            let fewest = graph
+                .nodes()
+                .filter(|v| !removed[*v as usize])
+                .map(remaining)
+                .min();
+            assert_eq!(fewest, Some(remaining(id)));
+            removed[id as usize] = true;
+        }
+        assert!(removed.iter().all(|r| *r));
+
+        assert_eq!(
+            "aa,ab,ac,ad",
+            solve(&parse_input(
+                "ab-aa ac-aa ad-aa ab-ac ab-ad ac-ad".to_string()
+            ))
+        );
+    }
+}
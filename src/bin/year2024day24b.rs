@@ -1,8 +1,4 @@
-use std::{
-    collections::{HashMap, HashSet},
-    fmt::Display,
-    str::FromStr,
-};
+use std::collections::{HashMap, HashSet};
 
 use aoc_2024_rs::*;
 
@@ -27,106 +23,57 @@ impl Op {
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord)]
 enum Label {
-    A(usize),
+    // Any other wire, by its id in the state's graph. Id 0 is always the unknown wire "".
+    A(u32),
     X(usize),
     Y(usize),
     Z(usize),
 }
 
-#[derive(Debug, PartialEq, Eq)]
-struct ParseLabelError;
-
-impl FromStr for Label {
-    type Err = ParseLabelError;
-
-    fn from_str(s: &str) -> Result<Self, Self::Err> {
-        if let Some(rest) = s.strip_prefix("x") {
-            Ok(Label::X(rest.chars().fold(0, |acc, ch| {
-                let digit: usize = ch.to_digit(10).unwrap().try_into().unwrap();
-                acc * 10 + digit
-            })))
-        } else if let Some(rest) = s.strip_prefix("y") {
-            Ok(Label::Y(rest.chars().fold(0, |acc, ch| {
-                let digit: usize = ch.to_digit(10).unwrap().try_into().unwrap();
-                acc * 10 + digit
-            })))
-        } else if let Some(rest) = s.strip_prefix("z") {
-            Ok(Label::Z(rest.chars().fold(0, |acc, ch| {
-                let digit: usize = ch.to_digit(10).unwrap().try_into().unwrap();
-                acc * 10 + digit
-            })))
-        } else {
-            Ok(Label::A(s.chars().fold(0, |acc, ch| {
-                let ascii: usize = (ch as u8).into();
-                acc * 1000 + ascii
-            })))
-        }
-    }
-}
-
-impl Display for Label {
-    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self {
-            Self::X(vs) => {
-                let mut s = String::new();
-                let mut v = *vs;
-                while v != 0 {
-                    let digit = v.rem_euclid(10);
-                    s.push_str(&digit.to_string());
-                    v /= 10;
-                }
-                s.push('x');
-                write!(f, "{}", s.chars().rev().collect::<String>())
-            }
-            Self::Y(vs) => {
-                let mut s = String::new();
-                let mut v = *vs;
-                while v != 0 {
-                    let digit = v.rem_euclid(10);
-                    s.push_str(&digit.to_string());
-                    v /= 10;
-                }
-                s.push('y');
-                write!(f, "{}", s.chars().rev().collect::<String>())
-            }
-            Self::Z(vs) => {
-                let mut s = String::new();
-                let mut v = *vs;
-                while v != 0 {
-                    let digit = v.rem_euclid(10);
-                    s.push_str(&digit.to_string());
-                    v /= 10;
-                }
-                s.push('z');
-                write!(f, "{}", s.chars().rev().collect::<String>())
-            }
-            Self::A(vs) => {
-                let mut s = String::new();
-                let mut v = *vs;
-                while v != 0 {
-                    let digit: u8 = v.rem_euclid(1000).try_into().unwrap();
-                    s.push(char::from(digit));
-                    v /= 1000;
-                }
-                write!(f, "{}", s.chars().rev().collect::<String>())
-            }
-        }
-    }
-}
-
 #[derive(Debug, Clone, PartialEq)]
 struct State {
     values: HashMap<Label, bool>,
     outputs: HashMap<Label, (Op, Label, Label)>,
     wires: HashSet<Label>,
+    // Every wire, with an edge from each gate input to its output.
+    graph: Graph,
 }
 
 impl State {
     fn new() -> Self {
+        let mut graph = Graph::directed();
+        graph.intern("");
         Self {
             values: HashMap::new(),
             outputs: HashMap::new(),
             wires: HashSet::new(),
+            graph,
+        }
+    }
+
+    fn label(&mut self, name: &str) -> Label {
+        let id = self.graph.intern(name);
+        let index = |prefix| {
+            name.strip_prefix(prefix)
+                .and_then(|rest| rest.parse::<usize>().ok())
+        };
+        if let Some(n) = index("x") {
+            Label::X(n)
+        } else if let Some(n) = index("y") {
+            Label::Y(n)
+        } else if let Some(n) = index("z") {
+            Label::Z(n)
+        } else {
+            Label::A(id)
+        }
+    }
+
+    fn name(&self, label: &Label) -> String {
+        match label {
+            Label::A(id) => self.graph.name(*id).to_string(),
+            Label::X(n) => format!("x{:02}", n),
+            Label::Y(n) => format!("y{:02}", n),
+            Label::Z(n) => format!("z{:02}", n),
         }
     }
 }
@@ -141,7 +88,7 @@ fn parse_input(input: String) -> State {
         }
         if line.contains(":") {
             let mut chunks = line.split_whitespace();
-            let wire = chunks.next().unwrap().trim_matches(':').parse().unwrap();
+            let wire = state.label(chunks.next().unwrap().trim_matches(':'));
             let value = match chunks.next().unwrap() {
                 "0" => false,
                 "1" => true,
@@ -153,16 +100,21 @@ fn parse_input(input: String) -> State {
             state.wires.insert(wire);
         } else if line.contains("->") {
             let mut chunks = line.split_whitespace();
-            let lhs = chunks.next().unwrap().parse().unwrap();
+            let lhs_name = chunks.next().unwrap();
             let op = match chunks.next().unwrap() {
                 "AND" => Op::And,
                 "XOR" => Op::Xor,
                 "OR" => Op::Or,
                 other => panic!("Unknown op: {:?}", other),
             };
-            let rhs = chunks.next().unwrap().parse().unwrap();
+            let rhs_name = chunks.next().unwrap();
             chunks.next();
-            let assign = chunks.next().unwrap().parse().unwrap();
+            let assign_name = chunks.next().unwrap();
+            state.graph.add_edge(lhs_name, assign_name);
+            state.graph.add_edge(rhs_name, assign_name);
+            let lhs = state.label(lhs_name);
+            let rhs = state.label(rhs_name);
+            let assign = state.label(assign_name);
             if let Some(prev) = state.outputs.insert(assign, (op, lhs, rhs)) {
                 panic!("Overwrote output formula for {:?}: {:?}", assign, prev);
             }
@@ -199,14 +151,14 @@ enum Child {
 
 impl Child {
     #[allow(dead_code)]
-    fn render(&self) -> String {
+    fn render(&self, state: &State) -> String {
         match self {
-            Self::Leaf(leaf) => leaf.to_string(),
+            Self::Leaf(leaf) => state.name(leaf),
             Self::Tree(tree) => format!(
                 "({} {} {})",
-                (*tree.lhs).render(),
+                (*tree.lhs).render(state),
                 tree.op.render(),
-                (*tree.rhs).render()
+                (*tree.rhs).render(state)
             ),
         }
     }
@@ -253,8 +205,8 @@ impl Tree {
     }
 
     #[allow(dead_code)]
-    fn render(self) -> String {
-        Child::Tree(self).render()
+    fn render(self, state: &State) -> String {
+        Child::Tree(self).render(state)
     }
 }
 
@@ -581,18 +533,14 @@ fn solve(parsed: &State) -> String {
         z += 1;
     }
 
-    let mut swaps: Vec<Label> = swaps
+    let mut swaps: Vec<String> = swaps
         .iter()
         .flat_map(|(a, b)| vec![a, b])
-        .cloned()
+        .map(|label| parsed.name(label))
         .collect();
     swaps.sort();
 
-    swaps
-        .iter()
-        .map(|label| label.to_string())
-        .collect::<Vec<String>>()
-        .join(",")
+    swaps.join(",")
 }
 
 fn main() {
@@ -608,9 +556,56 @@ mod tests {
 
     #[test]
     fn day24b_parse() {
-        assert_eq!("x123", Label::from_str("x123").unwrap().to_string());
-        assert_eq!("y987", Label::from_str("y987").unwrap().to_string());
-        assert_eq!("z555", Label::from_str("z555").unwrap().to_string());
-        assert_eq!("blah", Label::from_str("blah").unwrap().to_string());
+        let mut state = State::new();
+        for name in ["x123", "y987", "z555", "x05", "blah", "xqf"] {
+            let label = state.label(name);
+            assert_eq!(name, state.name(&label));
+        }
+        assert_eq!(Label::X(5), state.label("x05"));
+        assert_eq!(Label::A(0), state.label(""));
+        assert_eq!(state.label("blah"), state.label("blah"));
+    }
+
+    #[test]
+    fn day24b_wire_graph() {
+        let input = "
+x00: 1
+x01: 0
+y00: 1
+y01: 1
+
+x00 AND y00 -> abc
+x01 XOR y01 -> z01
+abc OR z01 -> z02
+x00 XOR y00 -> z00
+        "
+        .trim()
+        .to_string();
+        let state = parse_input(input);
+
+        let graph = &state.graph;
+        assert!(graph.is_directed());
+        // The unknown wire, 4 inputs, 3 zs, and abc.
+        assert_eq!(1 + 4 + 3 + 1, graph.len());
+        let abc = graph.id("abc").unwrap();
+        assert_eq!(
+            Label::A(abc),
+            *state
+                .outputs
+                .keys()
+                .find(|l| matches!(l, Label::A(_)))
+                .unwrap()
+        );
+        assert_eq!(2, graph.in_degree(abc));
+        assert_eq!(1, graph.degree(abc));
+        let x00 = graph.id("x00").unwrap();
+        let mut outs: Vec<&str> = graph
+            .neighbors(x00)
+            .iter()
+            .map(|id| graph.name(*id))
+            .collect();
+        outs.sort();
+        assert_eq!(vec!["abc", "z00"], outs);
+        assert_eq!(8, graph.edges().count());
     }
 }
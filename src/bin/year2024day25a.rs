@@ -1,5 +1,8 @@
 use aoc_2024_rs::*;
 
+// Write the full lock/key fit matrix next to the binary.
+const EXPORT_FITS: bool = false;
+
 #[derive(Debug, PartialEq)]
 struct State {
     locks: Vec<Vec<u32>>,
@@ -19,7 +22,87 @@ impl State {
     }
 }
 
+#[derive(Debug, PartialEq)]
+enum SchematicError {
+    WrongHeight {
+        schematic: usize,
+        expected: usize,
+        found: usize,
+    },
+    WrongWidth {
+        schematic: usize,
+        row: usize,
+        expected: usize,
+        found: usize,
+    },
+    BadChar {
+        schematic: usize,
+        row: usize,
+        col: usize,
+        found: char,
+    },
+    // Neither the top row (lock) nor the bottom row (key) is all '#', or both are.
+    NotLockOrKey {
+        schematic: usize,
+    },
+    // A pin with a gap in it, so it isn't a single column of '#' from the solid row.
+    Gap {
+        schematic: usize,
+        col: usize,
+    },
+}
+
+impl std::fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::WrongHeight {
+                schematic,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Schematic {} has {} rows, expected {}.",
+                schematic, found, expected
+            ),
+            Self::WrongWidth {
+                schematic,
+                row,
+                expected,
+                found,
+            } => write!(
+                f,
+                "Schematic {} row {} is {} wide, expected {}.",
+                schematic, row, found, expected
+            ),
+            Self::BadChar {
+                schematic,
+                row,
+                col,
+                found,
+            } => write!(
+                f,
+                "Schematic {} has {:?} at row {} col {}, expected '#' or '.'.",
+                schematic, found, row, col
+            ),
+            Self::NotLockOrKey { schematic } => write!(
+                f,
+                "Schematic {} needs exactly one of its top (lock) or bottom (key) rows solid.",
+                schematic
+            ),
+            Self::Gap { schematic, col } => write!(
+                f,
+                "Schematic {} column {} isn't contiguous from its solid row.",
+                schematic, col
+            ),
+        }
+    }
+}
+
 fn parse_input(input: String) -> State {
+    parse_input_sized(input, 5, 7).unwrap_or_else(|e| panic!("{}", e))
+}
+
+fn parse_input_sized(input: String, width: usize, rows: usize) -> Result<State, SchematicError> {
     let mut state = State::new();
 
     let mut chunks: Vec<Vec<&str>> = Vec::new();
@@ -34,44 +117,138 @@ fn parse_input(input: String) -> State {
         }
         chunks.iter_mut().last().unwrap().push(line);
     }
+    // Extra blank lines shouldn't count as empty schematics.
+    chunks.retain(|chunk| !chunk.is_empty());
 
-    state.height = chunks[0].len() - 2;
-    state.width = chunks[0][0].len();
+    state.height = rows - 2;
+    state.width = width;
 
-    for chunk in &chunks {
-        let mut heights = vec![0; chunk[0].len()];
-        for line in chunk {
+    for (i, chunk) in chunks.iter().enumerate() {
+        if chunk.len() != rows {
+            return Err(SchematicError::WrongHeight {
+                schematic: i,
+                expected: rows,
+                found: chunk.len(),
+            });
+        }
+
+        let mut grid: Vec<Vec<bool>> = Vec::new();
+        for (y, line) in chunk.iter().enumerate() {
+            let mut row = Vec::new();
             for (x, col) in line.chars().enumerate() {
-                heights[x] += if col == '#' { 1 } else { 0 };
+                row.push(match col {
+                    '#' => true,
+                    '.' => false,
+                    other => {
+                        return Err(SchematicError::BadChar {
+                            schematic: i,
+                            row: y,
+                            col: x,
+                            found: other,
+                        })
+                    }
+                });
+            }
+            if row.len() != width {
+                return Err(SchematicError::WrongWidth {
+                    schematic: i,
+                    row: y,
+                    expected: width,
+                    found: row.len(),
+                });
             }
+            grid.push(row);
         }
-        for height in &mut heights {
-            *height -= 1;
+
+        let is_lock = grid[0].iter().all(|c| *c);
+        let is_key = grid[rows - 1].iter().all(|c| *c);
+        if is_lock == is_key {
+            return Err(SchematicError::NotLockOrKey { schematic: i });
         }
-        if chunk[0].chars().all(|c| c == '#') {
+
+        // Read each column outwards from the solid row, once it stops it can't start again.
+        let mut heights = vec![0; width];
+        for (x, height) in heights.iter_mut().enumerate() {
+            let column: Vec<bool> = if is_lock {
+                grid.iter().map(|row| row[x]).collect()
+            } else {
+                grid.iter().rev().map(|row| row[x]).collect()
+            };
+            let filled = column.iter().take_while(|c| **c).count();
+            if column[filled..].iter().any(|c| *c) {
+                return Err(SchematicError::Gap {
+                    schematic: i,
+                    col: x,
+                });
+            }
+            *height = u32::try_from(filled).unwrap() - 1;
+        }
+
+        if is_lock {
             state.locks.push(heights);
         } else {
             state.keys.push(heights);
         }
     }
 
-    state
+    Ok(state)
+}
+
+impl State {
+    /// Every cell of the schematic as one bit, row by row, rebuilt from its pin heights (which
+    /// parsing checked are solid from the top for locks and the bottom for keys). A lock and key
+    /// fit exactly when no cell is filled in both, i.e. their masks AND to zero. Words of 64 so
+    /// any size works.
+    fn mask(&self, heights: &[u32], is_lock: bool) -> Vec<u64> {
+        let rows = self.height + 2;
+        let mut mask = vec![0; (self.width * rows).div_ceil(64)];
+        for (x, height) in heights.iter().enumerate() {
+            for filled in 0..=usize::try_from(*height).unwrap() {
+                let y = if is_lock { filled } else { rows - 1 - filled };
+                let bit = y * self.width + x;
+                mask[bit / 64] |= 1 << (bit % 64);
+            }
+        }
+        mask
+    }
+
+    fn lock_masks(&self) -> Vec<Vec<u64>> {
+        self.locks.iter().map(|l| self.mask(l, true)).collect()
+    }
+
+    fn key_masks(&self) -> Vec<Vec<u64>> {
+        self.keys.iter().map(|k| self.mask(k, false)).collect()
+    }
+}
+
+fn fits(lock: &[u64], key: &[u64]) -> bool {
+    lock.iter().zip(key.iter()).all(|(l, k)| l & k == 0)
 }
 
-fn can_fit(state: &State, lock: &[u32], key: &[u32]) -> bool {
-    for x in 0..state.width {
-        if key[x] + lock[x] > state.height.try_into().unwrap() {
-            return false;
+fn fits_to_csv(state: &State) -> String {
+    // One row per lock, one column per key, 1 where they fit.
+    let keys = state.key_masks();
+    let mut csv = String::from("lock");
+    for k in 0..keys.len() {
+        csv.push_str(&format!(",key_{}", k));
+    }
+    csv.push('\n');
+    for (l, lock) in state.lock_masks().iter().enumerate() {
+        csv.push_str(&l.to_string());
+        for key in &keys {
+            csv.push_str(if fits(lock, key) { ",1" } else { ",0" });
         }
+        csv.push('\n');
     }
-    true
+    csv
 }
 
 fn solve(parsed: &State) -> usize {
+    let locks = parsed.lock_masks();
     let mut accumulator = 0;
-    for key in &parsed.keys {
-        for lock in &parsed.locks {
-            if can_fit(parsed, lock, key) {
+    for key in &parsed.key_masks() {
+        for lock in &locks {
+            if fits(lock, key) {
                 accumulator += 1;
             }
         }
@@ -82,6 +259,10 @@ fn solve(parsed: &State) -> usize {
 fn main() {
     let input = load_input(2024, 25);
     let parsed = parse_input(input);
+    if EXPORT_FITS {
+        std::fs::write("year2024day25a-fits.csv", fits_to_csv(&parsed))
+            .expect("Write fits failed!");
+    }
     let answer = solve(&parsed);
     println!("Answer: {:?}", answer);
 }
@@ -137,33 +318,100 @@ mod tests {
         .to_string();
         let parsed = parse_input(input);
 
+        assert_eq!(vec![vec![0, 5, 3, 4, 3], vec![1, 2, 0, 5, 3]], parsed.locks);
         assert_eq!(
-            State {
-                locks: vec![vec![0, 5, 3, 4, 3], vec![1, 2, 0, 5, 3]],
-                keys: vec![
-                    vec![5, 0, 2, 1, 3],
-                    vec![4, 3, 4, 0, 2],
-                    vec![3, 0, 2, 0, 1],
-                ],
-                height: 5,
-                width: 5
-            },
-            parsed
+            vec![
+                vec![5, 0, 2, 1, 3],
+                vec![4, 3, 4, 0, 2],
+                vec![3, 0, 2, 0, 1],
+            ],
+            parsed.keys
         );
+        assert_eq!(5, parsed.height);
+        assert_eq!(5, parsed.width);
 
+        let locks = parsed.lock_masks();
+        let keys = parsed.key_masks();
         //Lock 0,5,3,4,3 and key 5,0,2,1,3: overlap in the last column.
-        assert_eq!(false, can_fit(&parsed, &parsed.locks[0], &parsed.keys[0]));
+        assert!(!fits(&locks[0], &keys[0]));
         //Lock 0,5,3,4,3 and key 4,3,4,0,2: overlap in the second column.
-        assert_eq!(false, can_fit(&parsed, &parsed.locks[0], &parsed.keys[1]));
+        assert!(!fits(&locks[0], &keys[1]));
         //Lock 0,5,3,4,3 and key 3,0,2,0,1: all columns fit!
-        assert_eq!(true, can_fit(&parsed, &parsed.locks[0], &parsed.keys[2]));
+        assert!(fits(&locks[0], &keys[2]));
         //Lock 1,2,0,5,3 and key 5,0,2,1,3: overlap in the first column.
-        assert_eq!(false, can_fit(&parsed, &parsed.locks[1], &parsed.keys[0]));
+        assert!(!fits(&locks[1], &keys[0]));
         //Lock 1,2,0,5,3 and key 4,3,4,0,2: all columns fit!
-        assert_eq!(true, can_fit(&parsed, &parsed.locks[1], &parsed.keys[1]));
+        assert!(fits(&locks[1], &keys[1]));
         //Lock 1,2,0,5,3 and key 3,0,2,0,1: all columns fit!
-        assert_eq!(true, can_fit(&parsed, &parsed.locks[1], &parsed.keys[2]));
+        assert!(fits(&locks[1], &keys[2]));
 
         assert_eq!(3, solve(&parsed));
     }
+
+    #[test]
+    fn day25a_validation() {
+        let lock = "#####\n.####\n.####\n.####\n.#.#.\n.#...\n.....";
+        let parsed = parse_input(format!("{}\n\n{}", lock, lock));
+        assert_eq!(2, parsed.locks.len());
+        assert_eq!("lock\n0\n1\n", fits_to_csv(&parsed));
+
+        let check = |input: &str| parse_input_sized(input.to_string(), 5, 7).unwrap_err();
+        assert_eq!(
+            SchematicError::WrongHeight {
+                schematic: 0,
+                expected: 7,
+                found: 6
+            },
+            check("#####\n.####\n.####\n.####\n.#.#.\n.....")
+        );
+        assert_eq!(
+            SchematicError::WrongWidth {
+                schematic: 1,
+                row: 2,
+                expected: 5,
+                found: 4
+            },
+            check(&format!(
+                "{}\n\n#####\n.####\n.###\n.####\n.#.#.\n.#...\n.....",
+                lock
+            ))
+        );
+        assert_eq!(
+            SchematicError::BadChar {
+                schematic: 0,
+                row: 4,
+                col: 2,
+                found: 'x'
+            },
+            check("#####\n.####\n.####\n.####\n.#x#.\n.#...\n.....")
+        );
+        assert_eq!(
+            SchematicError::NotLockOrKey { schematic: 0 },
+            check("####.\n.####\n.####\n.####\n.#.#.\n.#...\n.....")
+        );
+        let gap = check("#####\n.####\n.#.##\n.####\n.#.#.\n.#...\n.....");
+        assert_eq!(
+            SchematicError::Gap {
+                schematic: 0,
+                col: 2
+            },
+            gap
+        );
+        assert_eq!(
+            "Schematic 0 column 2 isn't contiguous from its solid row.",
+            gap.to_string()
+        );
+
+        // Wider and taller than the puzzle, spilling the masks over a 64 bit word.
+        let lock = "##########\n##########\n#########.\n#.#.#.#.#.\n..........\n..........\n..........\n..........";
+        let key = "..........\n..........\n..........\n..........\n.#.#.#.#.#\n.#.#.#.#.#\n.#.#.#.###\n##########";
+        let taller = "..........\n..........\n.#........\n.#........\n.#.#.#.#.#\n.#.#.#.#.#\n.#.#.#.###\n##########";
+        let parsed =
+            parse_input_sized(format!("{}\n\n{}\n\n{}", lock, key, taller), 10, 8).unwrap();
+        assert_eq!(2, parsed.lock_masks()[0].len());
+        assert_eq!(vec![vec![3, 2, 3, 2, 3, 2, 3, 2, 3, 1]], parsed.locks);
+        assert_eq!(vec![0, 3, 0, 3, 0, 3, 0, 3, 1, 3], parsed.keys[0]);
+        assert_eq!(1, solve(&parsed));
+        assert_eq!("lock,key_0,key_1\n0,1,0\n", fits_to_csv(&parsed));
+    }
 }
@@ -1,3 +1,5 @@
+pub mod regions;
+
 pub fn load_input(year: u16, day: u8) -> String {
     let profile = std::env::var("AOC_PROFILE").unwrap_or("default".to_string());
     let filename = format!("{}-{}-{:02}-input.txt", profile, year, day);
@@ -18,6 +20,230 @@ pub fn pause() {
         .expect("Read line failed!");
 }
 
+/// Greatest common divisor by Euclid's algorithm. For signed inputs the sign of the result
+/// follows the remainders, so take abs() if that matters.
+pub fn gcd<T: num_traits::PrimInt>(a: T, b: T) -> T {
+    if b.is_zero() {
+        a
+    } else {
+        gcd(b, a % b)
+    }
+}
+
+/// Memoisation for recursive functions. The cache is passed down explicitly, so there's no
+/// global state and nothing leaks between calls (or tests). Keys are hashed as-is with FxHash,
+/// rather than formatted into strings first.
+///
+/// ```
+/// use aoc_2024_rs::Memo;
+///
+/// fn fib(n: u64, memo: &mut Memo<u64, u64>) -> u64 {
+///     if n < 2 {
+///         return n;
+///     }
+///     memo.get_or_compute(n, |memo| fib(n - 1, memo) + fib(n - 2, memo))
+/// }
+///
+/// let mut memo = Memo::new();
+/// assert_eq!(12586269025, fib(50, &mut memo));
+/// assert_eq!(49, memo.misses());
+/// ```
+#[derive(Debug, Clone)]
+pub struct Memo<K, V> {
+    cache: rustc_hash::FxHashMap<K, V>,
+    hits: usize,
+    misses: usize,
+}
+
+impl<K: std::hash::Hash + Eq, V: Clone> Default for Memo<K, V> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: std::hash::Hash + Eq, V: Clone> Memo<K, V> {
+    pub fn new() -> Self {
+        Self {
+            cache: rustc_hash::FxHashMap::default(),
+            hits: 0,
+            misses: 0,
+        }
+    }
+
+    /// Returns the cached value for key, or computes it with f (which gets the memo back, so it
+    /// can recurse) and caches that.
+    pub fn get_or_compute(&mut self, key: K, f: impl FnOnce(&mut Self) -> V) -> V {
+        if let Some(value) = self.cache.get(&key) {
+            self.hits += 1;
+            return value.clone();
+        }
+        self.misses += 1;
+        let value = f(self);
+        self.cache.insert(key, value.clone());
+        value
+    }
+
+    pub fn hits(&self) -> usize {
+        self.hits
+    }
+
+    pub fn misses(&self) -> usize {
+        self.misses
+    }
+
+    pub fn len(&self) -> usize {
+        self.cache.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.cache.is_empty()
+    }
+}
+
+/// A graph over named nodes. Names are interned to dense `u32` ids the first time they're seen,
+/// so everything after parsing can work on plain integers (and index straight into vectors)
+/// instead of hashing and cloning strings. Neighbour lists are kept sorted, so edge checks are a
+/// binary search and iteration order doesn't depend on hashing.
+///
+/// ```
+/// use aoc_2024_rs::Graph;
+///
+/// let mut graph = Graph::undirected();
+/// let (a, b) = graph.add_edge("kh", "tc");
+/// graph.add_edge("tc", "kh");
+/// assert_eq!(2, graph.len());
+/// assert!(graph.has_edge(b, a));
+/// assert_eq!(vec![(a, b)], graph.edges().collect::<Vec<_>>());
+/// assert_eq!("tc", graph.name(b));
+///
+/// let mut graph = Graph::directed();
+/// let (a, b) = graph.add_edge("x00", "z00");
+/// assert!(graph.has_edge(a, b) && !graph.has_edge(b, a));
+/// assert_eq!((1, 0), (graph.degree(a), graph.in_degree(a)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Graph {
+    directed: bool,
+    names: Vec<String>,
+    ids: rustc_hash::FxHashMap<String, u32>,
+    outgoing: Vec<Vec<u32>>,
+    // Only filled in for directed graphs, undirected ones just use outgoing both ways.
+    incoming: Vec<Vec<u32>>,
+}
+
+impl Graph {
+    pub fn new(directed: bool) -> Self {
+        Self {
+            directed,
+            ..Default::default()
+        }
+    }
+
+    pub fn undirected() -> Self {
+        Self::new(false)
+    }
+
+    pub fn directed() -> Self {
+        Self::new(true)
+    }
+
+    pub fn is_directed(&self) -> bool {
+        self.directed
+    }
+
+    /// Returns the id for name, adding it as a new (unconnected) node if it hasn't been seen.
+    pub fn intern(&mut self, name: &str) -> u32 {
+        if let Some(id) = self.ids.get(name) {
+            return *id;
+        }
+        let id = u32::try_from(self.names.len()).unwrap();
+        self.names.push(name.to_string());
+        self.ids.insert(name.to_string(), id);
+        self.outgoing.push(Vec::new());
+        self.incoming.push(Vec::new());
+        id
+    }
+
+    pub fn id(&self, name: &str) -> Option<u32> {
+        self.ids.get(name).copied()
+    }
+
+    pub fn name(&self, id: u32) -> &str {
+        &self.names[id as usize]
+    }
+
+    pub fn len(&self) -> usize {
+        self.names.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.names.is_empty()
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = u32> {
+        0..u32::try_from(self.names.len()).unwrap()
+    }
+
+    /// Interns both names and connects them, returning their ids. Adding an edge twice is a no-op.
+    pub fn add_edge(&mut self, from: &str, to: &str) -> (u32, u32) {
+        let from = self.intern(from);
+        let to = self.intern(to);
+        self.add_edge_ids(from, to);
+        (from, to)
+    }
+
+    pub fn add_edge_ids(&mut self, from: u32, to: u32) {
+        fn insert_sorted(list: &mut Vec<u32>, id: u32) {
+            if let Err(i) = list.binary_search(&id) {
+                list.insert(i, id);
+            }
+        }
+        insert_sorted(&mut self.outgoing[from as usize], to);
+        if self.directed {
+            insert_sorted(&mut self.incoming[to as usize], from);
+        } else {
+            insert_sorted(&mut self.outgoing[to as usize], from);
+        }
+    }
+
+    pub fn has_edge(&self, from: u32, to: u32) -> bool {
+        self.neighbors(from).binary_search(&to).is_ok()
+    }
+
+    /// Nodes this one has an edge to, sorted by id.
+    pub fn neighbors(&self, id: u32) -> &[u32] {
+        &self.outgoing[id as usize]
+    }
+
+    /// Nodes with an edge to this one, sorted by id. Same as neighbors when undirected.
+    pub fn predecessors(&self, id: u32) -> &[u32] {
+        if self.directed {
+            &self.incoming[id as usize]
+        } else {
+            &self.outgoing[id as usize]
+        }
+    }
+
+    /// Out-degree when directed.
+    pub fn degree(&self, id: u32) -> usize {
+        self.neighbors(id).len()
+    }
+
+    pub fn in_degree(&self, id: u32) -> usize {
+        self.predecessors(id).len()
+    }
+
+    /// Every edge once, ordered by (from, to). Undirected edges come out as (lower, higher).
+    pub fn edges(&self) -> impl Iterator<Item = (u32, u32)> + '_ {
+        self.nodes().flat_map(move |from| {
+            self.neighbors(from)
+                .iter()
+                .filter(move |to| self.directed || from <= **to)
+                .map(move |to| (from, *to))
+        })
+    }
+}
+
 // Trait aliases are experimental?
 //trait Number = num_traits::PrimInt + num_traits::Signed;
 
@@ -45,6 +271,10 @@ impl<T: num_traits::PrimInt + num_traits::Signed> Point2<T> {
     pub fn manhattan_distance(&self, other: &Point2<T>) -> T {
         (self.x - other.x).abs() + (self.y - other.y).abs()
     }
+
+    pub fn chebyshev_distance(&self, other: &Point2<T>) -> T {
+        (self.x - other.x).abs().max((self.y - other.y).abs())
+    }
 }
 
 #[derive(Debug, PartialEq, Clone)]
@@ -0,0 +1,295 @@
+//! Labelled regions on a char grid: flood filling them out, measuring them, and drawing their
+//! outlines. A region is a 4-connected group of cells sharing the same char.
+
+use rustc_hash::{FxHashMap, FxHashSet};
+
+use crate::{BBox2, Direction, Point2};
+
+pub type Grid = FxHashMap<Point2<i32>, char>;
+
+const DIRECTIONS: [Direction; 4] = [
+    Direction::North,
+    Direction::East,
+    Direction::South,
+    Direction::West,
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Region {
+    pub label: char,
+    pub cells: FxHashSet<Point2<i32>>,
+    pub area: usize,
+    // Cell edges facing out of the region (including into holes).
+    pub perimeter: usize,
+    // Straight runs of perimeter, which is the same as the number of corners.
+    pub sides: usize,
+    // Pockets of other cells (or nothing) completely surrounded by this region.
+    pub holes: usize,
+    pub bbox: BBox2<i32>,
+}
+
+impl Region {
+    /// Measures the given cells, which should be one 4-connected region (see flood_fill). No
+    /// cells gives a region with everything 0 (and the default, empty, bbox).
+    ///
+    /// ```
+    /// use aoc_2024_rs::regions::Region;
+    ///
+    /// let region = Region::new('A', Default::default());
+    /// assert_eq!((0, 0, 0, 0), (region.area, region.perimeter, region.sides, region.holes));
+    /// ```
+    pub fn new(label: char, cells: FxHashSet<Point2<i32>>) -> Self {
+        let mut bbox = BBox2::default();
+        let mut perimeter = 0;
+        let mut sides = 0;
+        for cell in &cells {
+            bbox.update(cell);
+            for direction in DIRECTIONS {
+                if !cells.contains(&direction.step(cell)) {
+                    perimeter += 1;
+                }
+                // Check the corner between this direction and the next one clockwise. It's an
+                // outside corner if neither side is in the region, and an inside corner if both
+                // are but the diagonal isn't.
+                let side = direction.step(cell);
+                let other = direction.rotate_right().step(cell);
+                let diagonal = direction.rotate_right().step(&side);
+                match (cells.contains(&side), cells.contains(&other)) {
+                    (false, false) => sides += 1,
+                    (true, true) if !cells.contains(&diagonal) => sides += 1,
+                    _ => {}
+                }
+            }
+        }
+
+        let mut region = Self {
+            label,
+            area: cells.len(),
+            cells,
+            perimeter,
+            sides,
+            holes: 0,
+            bbox,
+        };
+        region.holes = count_holes(&region);
+        region
+    }
+
+    /// Closed outlines around the region, as the corner points of each polygon (cell x,y covers
+    /// x..x+1 and y..y+1). The outer boundary goes clockwise (with y pointing down) and holes go
+    /// anticlockwise, so the region is always on the right.
+    pub fn outlines(&self) -> Vec<Vec<Point2<i32>>> {
+        // Every exposed cell edge, keyed by where it starts, walking with the region on the
+        // right.
+        let mut edges: FxHashMap<Point2<i32>, Vec<Direction>> = FxHashMap::default();
+        for cell in &self.cells {
+            let (x, y) = (cell.x, cell.y);
+            for (direction, start, heading) in [
+                (Direction::North, Point2::new(x, y), Direction::East),
+                (Direction::East, Point2::new(x + 1, y), Direction::South),
+                (Direction::South, Point2::new(x + 1, y + 1), Direction::West),
+                (Direction::West, Point2::new(x, y + 1), Direction::North),
+            ] {
+                if !self.cells.contains(&direction.step(cell)) {
+                    edges.entry(start).or_default().push(heading);
+                }
+            }
+        }
+
+        let mut starts: Vec<Point2<i32>> = edges.keys().copied().collect();
+        starts.sort_by_key(|p| (p.y, p.x));
+
+        let mut outlines = Vec::new();
+        for start in starts {
+            while let Some(first) = edges.get_mut(&start).and_then(|ds| ds.pop()) {
+                let mut outline = Vec::new();
+                let mut heading = first;
+                let mut at = first.step(&start);
+                while at != start {
+                    // Where two cells only touch at a corner there are two ways on. Turning left
+                    // wraps around the cell that isn't in the region, which keeps holes that only
+                    // touch at a corner as separate loops (same as count_holes).
+                    let options = edges.get_mut(&at).unwrap();
+                    let next = [heading.rotate_left(), heading, heading.rotate_right()]
+                        .into_iter()
+                        .find(|d| options.contains(d))
+                        .unwrap();
+                    options.retain(|d| *d != next);
+                    if next != heading {
+                        outline.push(at);
+                    }
+                    heading = next;
+                    at = next.step(&at);
+                }
+                // The start is only a corner if the loop turns there.
+                if heading != first {
+                    outline.insert(0, start);
+                }
+                outlines.push(outline);
+            }
+        }
+
+        outlines
+    }
+}
+
+fn count_holes(region: &Region) -> usize {
+    // The bbox is inverted when there are no cells, so there'd be nothing sensible to flood.
+    if region.cells.is_empty() {
+        return 0;
+    }
+
+    // Flood the outside in from one cell past the bounding box, then any cells left over that
+    // aren't part of the region are enclosed. Each 4-connected group of those is a hole.
+    let bbox = BBox2::new(
+        &Point2::new(region.bbox.min.x - 1, region.bbox.min.y - 1),
+        &Point2::new(region.bbox.max.x + 1, region.bbox.max.y + 1),
+    );
+
+    let mut seen: FxHashSet<Point2<i32>> = FxHashSet::default();
+    let fill = |start: Point2<i32>, seen: &mut FxHashSet<Point2<i32>>| {
+        let mut queue = vec![start];
+        seen.insert(start);
+        while let Some(at) = queue.pop() {
+            for direction in DIRECTIONS {
+                let n = direction.step(&at);
+                if bbox.contains(&n) && !region.cells.contains(&n) && seen.insert(n) {
+                    queue.push(n);
+                }
+            }
+        }
+    };
+
+    fill(bbox.min, &mut seen);
+
+    let mut holes = 0;
+    for y in bbox.min.y..=bbox.max.y {
+        for x in bbox.min.x..=bbox.max.x {
+            let p = Point2::new(x, y);
+            if !region.cells.contains(&p) && !seen.contains(&p) {
+                holes += 1;
+                fill(p, &mut seen);
+            }
+        }
+    }
+    holes
+}
+
+/// All the cells connected to start with the same label.
+pub fn flood_fill(grid: &Grid, start: &Point2<i32>) -> FxHashSet<Point2<i32>> {
+    let mut region = FxHashSet::default();
+
+    let label = grid.get(start).unwrap();
+    let mut queue = vec![*start];
+    while let Some(at) = queue.pop() {
+        if region.contains(&at) || grid.get(&at) != Some(label) {
+            continue;
+        }
+        region.insert(at);
+        for direction in DIRECTIONS {
+            queue.push(direction.step(&at));
+        }
+    }
+
+    region
+}
+
+/// Every region in the grid, in reading order of their top left cell.
+///
+/// ```
+/// use aoc_2024_rs::regions::{get_regions, Grid};
+/// use aoc_2024_rs::Point2;
+///
+/// let mut grid = Grid::default();
+/// for (y, row) in ["OOOOO", "OXOXO", "OOOOO"].iter().enumerate() {
+///     for (x, c) in row.chars().enumerate() {
+///         grid.insert(Point2::new(x as i32, y as i32), c);
+///     }
+/// }
+///
+/// let regions = get_regions(&grid);
+/// assert_eq!(3, regions.len());
+/// let o = &regions[0];
+/// assert_eq!(('O', 13, 24, 12, 2), (o.label, o.area, o.perimeter, o.sides, o.holes));
+/// assert_eq!(3, o.outlines().len());
+/// ```
+pub fn get_regions(grid: &Grid) -> Vec<Region> {
+    let mut cells: Vec<&Point2<i32>> = grid.keys().collect();
+    cells.sort_by_key(|p| (p.y, p.x));
+
+    let mut seen: FxHashSet<Point2<i32>> = FxHashSet::default();
+    let mut regions = Vec::new();
+    for at in cells {
+        if seen.contains(at) {
+            continue;
+        }
+        let region = flood_fill(grid, at);
+        seen.extend(region.iter().copied());
+        regions.push(Region::new(grid[at], region));
+    }
+
+    regions
+}
+
+/// An SVG with one filled path per region (holes cut out), coloured by label.
+///
+/// ```
+/// use aoc_2024_rs::regions::{to_svg, Region};
+/// use aoc_2024_rs::Point2;
+///
+/// let region = Region::new('<', [Point2::new(0, 0)].into_iter().collect());
+/// assert!(to_svg(&[region]).contains("<title>&lt;</title>"));
+/// assert!(to_svg(&[]).contains("viewBox=\"0 0 0 0\""));
+/// ```
+pub fn to_svg(regions: &[Region]) -> String {
+    let mut bbox = BBox2::default();
+    for region in regions.iter().filter(|r| !r.cells.is_empty()) {
+        bbox.update(&region.bbox.min);
+        bbox.update(&region.bbox.max);
+    }
+    // Nothing to draw leaves the bbox inverted, which would overflow working out the size.
+    if bbox.min.x > bbox.max.x {
+        bbox = BBox2 {
+            min: Point2::new(0, 0),
+            max: Point2::new(-1, -1),
+        };
+    }
+
+    let mut svg = format!(
+        "<svg xmlns=\"http://www.w3.org/2000/svg\" viewBox=\"{} {} {} {}\">\n",
+        bbox.min.x,
+        bbox.min.y,
+        bbox.max.x - bbox.min.x + 1,
+        bbox.max.y - bbox.min.y + 1
+    );
+    for region in regions {
+        let path: Vec<String> = region
+            .outlines()
+            .iter()
+            .map(|outline| {
+                let points: Vec<String> =
+                    outline.iter().map(|p| format!("{} {}", p.x, p.y)).collect();
+                format!("M{}Z", points.join("L"))
+            })
+            .collect();
+        svg.push_str(&format!(
+            "  <path d=\"{}\" fill=\"hsl({}, 60%, 70%)\" fill-rule=\"evenodd\" stroke=\"black\" stroke-width=\"0.05\"><title>{}</title></path>\n",
+            path.join(""),
+            (region.label as u32 * 47) % 360,
+            escape(region.label)
+        ));
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+fn escape(c: char) -> String {
+    match c {
+        '&' => "&amp;".to_string(),
+        '<' => "&lt;".to_string(),
+        '>' => "&gt;".to_string(),
+        '"' => "&quot;".to_string(),
+        '\'' => "&apos;".to_string(),
+        c => c.to_string(),
+    }
+}